@@ -0,0 +1,353 @@
+//! Contains an async counterpart of [`crate::IchiranCli`] and the [`AsyncBackend`] trait it uses to call `ichiran-cli`.
+
+use crate::{
+    process,
+    request::{Mode, Request},
+    Backend, IchiranCli, IchiranError, ProcessBackend, RomanizedWithInfo, Segment,
+};
+use std::{
+    future::Future,
+    path::PathBuf,
+    pin::Pin,
+    process::{Output, Stdio},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt},
+    process::Command,
+};
+
+/// The future returned by [`AsyncBackend::run_async`].
+pub type BackendFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<(String, String), IchiranError>> + Send + 'a>>;
+
+/// Async counterpart of [`Backend`], used by [`AsyncIchiranCli`] to call `ichiran-cli`.
+///
+/// [`ProcessBackend`] implements this trait by spawning the process with `tokio`.
+/// Other backends can be used through [`BlockingBackend`].
+pub trait AsyncBackend {
+    /// Runs `ichiran-cli` with the given arguments, followed by the input as the last argument.
+    /// Returns the stdout and stderr of a successful run.
+    /// Unsuccessful runs should return [`IchiranError::IchiranError`].
+    fn run_async<'a>(&'a self, args: &'a [&'a str], input: &'a str) -> BackendFuture<'a>;
+}
+
+impl<B: AsyncBackend + ?Sized> AsyncBackend for &B {
+    fn run_async<'a>(&'a self, args: &'a [&'a str], input: &'a str) -> BackendFuture<'a> {
+        (**self).run_async(args, input)
+    }
+}
+
+impl<B: AsyncBackend + ?Sized> AsyncBackend for Box<B> {
+    fn run_async<'a>(&'a self, args: &'a [&'a str], input: &'a str) -> BackendFuture<'a> {
+        (**self).run_async(args, input)
+    }
+}
+
+impl<B: AsyncBackend + ?Sized> AsyncBackend for Arc<B> {
+    fn run_async<'a>(&'a self, args: &'a [&'a str], input: &'a str) -> BackendFuture<'a> {
+        (**self).run_async(args, input)
+    }
+}
+
+impl AsyncBackend for ProcessBackend {
+    fn run_async<'a>(&'a self, args: &'a [&'a str], input: &'a str) -> BackendFuture<'a> {
+        Box::pin(async move {
+            let mut command = Command::from(self.command());
+            command.args(args).arg(input);
+            crate::parse_output(output(command, self.timeout).await?)
+        })
+    }
+}
+
+/// Adapts a [`Backend`] into an [`AsyncBackend`] by running each call on `tokio`'s blocking thread pool.
+///
+/// Allows the fixture, recording, replay and caching backends to be used with [`AsyncIchiranCli`].
+/// ```no_run
+/// # use ichiran::{AsyncIchiranCli, BlockingBackend, FixtureBackend};
+/// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// let backend = FixtureBackend::from_dir("./fixtures")?;
+/// let cli = AsyncIchiranCli::with_backend(BlockingBackend::new(backend));
+/// let segmentation = cli.segment("一覧は最高だぞ", None).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct BlockingBackend<B> {
+    backend: Arc<B>,
+}
+
+impl<B> BlockingBackend<B> {
+    /// Wraps the given backend.
+    pub fn new(backend: B) -> Self {
+        Self {
+            backend: Arc::new(backend),
+        }
+    }
+
+    /// Returns the wrapped backend.
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+}
+
+impl<B> Clone for BlockingBackend<B> {
+    fn clone(&self) -> Self {
+        Self {
+            backend: Arc::clone(&self.backend),
+        }
+    }
+}
+
+impl<B: Backend + Send + Sync + 'static> AsyncBackend for BlockingBackend<B> {
+    fn run_async<'a>(&'a self, args: &'a [&'a str], input: &'a str) -> BackendFuture<'a> {
+        let backend = Arc::clone(&self.backend);
+        let args = args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        let input = input.to_string();
+        Box::pin(async move {
+            tokio::task::spawn_blocking(move || {
+                let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+                backend.run(&args, &input)
+            })
+            .await
+            .map_err(|e| IchiranError::CommandError(e.into()))?
+        })
+    }
+}
+
+/// Async wrapper for ichiran-cli, built on `tokio`.
+///
+/// Returns the same results as [`IchiranCli`] without blocking the calling thread.
+/// The configuration of the [`IchiranCli`] the wrapper was created from is respected,
+/// and the child process is killed if the returned future is dropped before completion.
+#[derive(Debug, Clone)]
+pub struct AsyncIchiranCli<B = ProcessBackend> {
+    cli: IchiranCli<B>,
+}
+
+impl AsyncIchiranCli {
+    /// Takes a path to the `ichiran-cli` binary.
+    pub fn new(cli_path: PathBuf) -> Self {
+        Self {
+            cli: IchiranCli::new(cli_path),
+        }
+    }
+}
+
+impl<B: AsyncBackend> AsyncIchiranCli<B> {
+    /// Uses the given backend to call `ichiran-cli`.
+    pub fn with_backend(backend: B) -> Self {
+        Self {
+            cli: IchiranCli::with_backend(backend),
+        }
+    }
+
+    /// Returns the backend used to call `ichiran-cli`.
+    pub fn backend(&self) -> &B {
+        self.cli.backend()
+    }
+
+    /// Calls and parses the output of `ichiran-cli -f`.
+    /// The optional limit argument defines the max number of alternative segmentations that are returned for each segment.
+    pub async fn segment(
+        &self,
+        input: &str,
+        limit: Option<u32>,
+    ) -> Result<Vec<Segment>, IchiranError> {
+        let request = self.cli.request(Mode::Full(self.cli.limit(limit)), input);
+        request.segment(self.run_request(&request).await?)
+    }
+
+    /// Calls and parses the output of `ichiran-cli -i`.
+    pub async fn romanize_with_info(&self, input: &str) -> Result<RomanizedWithInfo, IchiranError> {
+        let request = self.cli.request(Mode::Info, input);
+        request.romanize_with_info(self.run_request(&request).await?)
+    }
+
+    /// Calls and parses the output of `ichiran-cli` without any flags.
+    pub async fn romanize(&self, input: &str) -> Result<String, IchiranError> {
+        let request = self.cli.request(Mode::Plain, input);
+        request.romanize(self.run_request(&request).await?)
+    }
+
+    /// Calls `ichiran-cli` for each input of the request and returns the stdout of the calls.
+    async fn run_request(&self, request: &Request<'_>) -> Result<Vec<String>, IchiranError> {
+        let args = request.mode.args();
+        let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+        let mut stdouts = vec![];
+        for input in request.inputs() {
+            let (stdout, _stderr) = self.cli.backend.run_async(&args, input).await?;
+            stdouts.push(stdout);
+        }
+        Ok(stdouts)
+    }
+}
+
+impl<B> From<IchiranCli<B>> for AsyncIchiranCli<B> {
+    fn from(cli: IchiranCli<B>) -> Self {
+        Self { cli }
+    }
+}
+
+/// Async counterpart of [`crate::process::output`].
+/// Runs the command to completion and collects its output.
+/// If the timeout is exceeded, the process is killed and [`IchiranError::Timeout`] is returned.
+async fn output(mut command: Command, timeout: Option<Duration>) -> Result<Output, IchiranError> {
+    let start = Instant::now();
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(IchiranError::CommandError)?;
+    let stdout = Arc::new(Mutex::new(Vec::new()));
+    let stderr = Arc::new(Mutex::new(Vec::new()));
+    let mut stdout_reader = tokio::spawn(read_into(
+        child.stdout.take().expect("stdout is piped"),
+        Arc::clone(&stdout),
+    ));
+    let mut stderr_reader = tokio::spawn(read_into(
+        child.stderr.take().expect("stderr is piped"),
+        Arc::clone(&stderr),
+    ));
+    let finished = async {
+        let status = child.wait().await;
+        let _ = (&mut stdout_reader).await;
+        let _ = (&mut stderr_reader).await;
+        status
+    };
+    let status = match timeout {
+        // the output is read within the timeout, since a grandchild process can keep the pipes open
+        Some(timeout) => match tokio::time::timeout(timeout, finished).await {
+            Ok(status) => status,
+            Err(_) => {
+                let _ = child.kill().await;
+                // the readers are not awaited in case a grandchild process is keeping the pipes open
+                stdout_reader.abort();
+                stderr_reader.abort();
+                let stderr = String::from_utf8_lossy(&process::snapshot(&stderr)).into_owned();
+                return Err(IchiranError::Timeout {
+                    elapsed: start.elapsed(),
+                    stderr,
+                });
+            }
+        },
+        None => finished.await,
+    }
+    .map_err(IchiranError::CommandError)?;
+    Ok(Output {
+        status,
+        stdout: process::snapshot(&stdout),
+        stderr: process::snapshot(&stderr),
+    })
+}
+
+/// Reads from the pipe until it is closed, appending to the buffer as data arrives
+/// so that partial output is available if the process times out.
+async fn read_into(mut pipe: impl AsyncRead + Unpin, buf: Arc<Mutex<Vec<u8>>>) {
+    let mut chunk = [0; 1024];
+    while let Ok(read) = pipe.read(&mut chunk).await {
+        if read == 0 {
+            break;
+        }
+        process::lock(&buf).extend_from_slice(&chunk[..read]);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing;
+
+    fn ichiran() -> AsyncIchiranCli {
+        AsyncIchiranCli::new(PathBuf::from("./data/ichiran-cli"))
+    }
+
+    #[tokio::test]
+    async fn matches_sync() {
+        let sync = IchiranCli::new(PathBuf::from("./data/ichiran-cli"));
+        let input = "一覧は最高だぞ";
+        assert_eq!(
+            ichiran().romanize(input).await.unwrap(),
+            sync.romanize(input).unwrap()
+        );
+        assert_eq!(
+            ichiran().romanize_with_info(input).await.unwrap(),
+            sync.romanize_with_info(input).unwrap()
+        );
+        assert_eq!(
+            ichiran().segment(input, Some(2)).await.unwrap(),
+            sync.segment(input, Some(2)).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn matches_sync_with_blocking_backend() {
+        let sync = testing::fixtures();
+        let cli = AsyncIchiranCli::with_backend(BlockingBackend::new(sync.backend().clone()));
+        let input = "一覧は最高だぞ";
+        assert_eq!(
+            cli.romanize(input).await.unwrap(),
+            sync.romanize(input).unwrap()
+        );
+        assert_eq!(
+            cli.romanize_with_info(input).await.unwrap(),
+            sync.romanize_with_info(input).unwrap()
+        );
+        assert_eq!(
+            cli.segment(input, None).await.unwrap(),
+            sync.segment(input, None).unwrap()
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn kills_on_timeout() {
+        let cli = AsyncIchiranCli::from(
+            IchiranCli::builder("sh")
+                .args(["-c", "echo partial >&2; sleep 10"])
+                .timeout(Duration::from_millis(500))
+                .build(),
+        );
+        let err = cli.romanize("input").await.unwrap_err();
+        let IchiranError::Timeout { elapsed, stderr } = err else {
+            panic!("{err:?}");
+        };
+        assert!(elapsed >= Duration::from_millis(500));
+        assert!(elapsed < Duration::from_secs(10));
+        assert_eq!(stderr, "partial\n");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn does_not_wait_for_grandchildren_past_timeout() {
+        let cli = AsyncIchiranCli::from(
+            IchiranCli::builder("sh")
+                // the background sleep inherits the pipes and keeps them open after sh exits
+                .args(["-c", "echo partial >&2; sleep 5 &"])
+                .timeout(Duration::from_millis(500))
+                .build(),
+        );
+        let err = cli.romanize("input").await.unwrap_err();
+        let IchiranError::Timeout { elapsed, stderr } = err else {
+            panic!("{err:?}");
+        };
+        assert!(elapsed >= Duration::from_millis(500));
+        assert!(elapsed < Duration::from_secs(5));
+        assert_eq!(stderr, "partial\n");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn finishes_before_timeout() {
+        let cli = AsyncIchiranCli::from(
+            IchiranCli::builder("sh")
+                .args(["-c", "echo out; echo err >&2"])
+                .timeout(Duration::from_secs(10))
+                .build(),
+        );
+        assert_eq!(cli.romanize("input").await.unwrap(), "out");
+    }
+}
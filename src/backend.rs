@@ -0,0 +1,85 @@
+//! Contains the [`Backend`] trait used by [`crate::IchiranCli`] to call `ichiran-cli`,
+//! and the default implementation that spawns a local process.
+
+use crate::{process, IchiranError};
+use std::{ffi::OsString, path::PathBuf, process::Command, rc::Rc, sync::Arc, time::Duration};
+
+/// Runs `ichiran-cli` and returns its output.
+///
+/// Implementing this trait allows the parsing in [`crate::IchiranCli`] to be reused with something other than a local `ichiran-cli` binary,
+/// such as a remote worker or an in-memory fake for tests.
+pub trait Backend {
+    /// Runs `ichiran-cli` with the given arguments, followed by the input as the last argument.
+    /// Returns the stdout and stderr of a successful run.
+    /// Unsuccessful runs should return [`IchiranError::IchiranError`].
+    fn run(&self, args: &[&str], input: &str) -> Result<(String, String), IchiranError>;
+}
+
+impl<B: Backend + ?Sized> Backend for &B {
+    fn run(&self, args: &[&str], input: &str) -> Result<(String, String), IchiranError> {
+        (**self).run(args, input)
+    }
+}
+
+impl<B: Backend + ?Sized> Backend for Box<B> {
+    fn run(&self, args: &[&str], input: &str) -> Result<(String, String), IchiranError> {
+        (**self).run(args, input)
+    }
+}
+
+impl<B: Backend + ?Sized> Backend for Rc<B> {
+    fn run(&self, args: &[&str], input: &str) -> Result<(String, String), IchiranError> {
+        (**self).run(args, input)
+    }
+}
+
+impl<B: Backend + ?Sized> Backend for Arc<B> {
+    fn run(&self, args: &[&str], input: &str) -> Result<(String, String), IchiranError> {
+        (**self).run(args, input)
+    }
+}
+
+/// The default backend, which spawns a new `ichiran-cli` process for every call.
+/// Usually configured through [`crate::IchiranCliBuilder`].
+#[derive(Debug, Clone)]
+pub struct ProcessBackend {
+    pub(crate) program: PathBuf,
+    pub(crate) prefix_args: Vec<OsString>,
+    pub(crate) envs: Vec<(OsString, OsString)>,
+    pub(crate) current_dir: Option<PathBuf>,
+    pub(crate) timeout: Option<Duration>,
+}
+
+impl ProcessBackend {
+    /// Takes the program to run, usually a path to the `ichiran-cli` binary.
+    pub fn new(program: impl Into<PathBuf>) -> Self {
+        Self {
+            program: program.into(),
+            prefix_args: vec![],
+            envs: vec![],
+            current_dir: None,
+            timeout: None,
+        }
+    }
+
+    /// Creates a command for calling `ichiran-cli` with the configured prefix arguments, environment and working directory.
+    pub(crate) fn command(&self) -> Command {
+        let mut command = Command::new(&self.program);
+        command
+            .args(&self.prefix_args)
+            .envs(self.envs.iter().map(|(k, v)| (k, v)));
+        if let Some(current_dir) = &self.current_dir {
+            command.current_dir(current_dir);
+        }
+        command
+    }
+}
+
+impl Backend for ProcessBackend {
+    fn run(&self, args: &[&str], input: &str) -> Result<(String, String), IchiranError> {
+        let mut command = self.command();
+        command.args(args).arg(input);
+        let out = process::output(command, self.timeout)?;
+        crate::parse_output(out)
+    }
+}
@@ -0,0 +1,71 @@
+//! Contains the worker pool used for batch processing.
+
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+/// Calls `f` for every input on a pool of at most `workers` threads,
+/// returning the results in the same order as the inputs.
+pub(crate) fn run<S, T, F>(inputs: Vec<S>, workers: usize, f: F) -> Vec<T>
+where
+    S: Sync,
+    T: Send,
+    F: Fn(&S) -> T + Sync,
+{
+    let workers = workers.max(1).min(inputs.len());
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new((0..inputs.len()).map(|_| None).collect::<Vec<_>>());
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let idx = next.fetch_add(1, Ordering::SeqCst);
+                let input = match inputs.get(idx) {
+                    Some(input) => input,
+                    None => break,
+                };
+                let result = f(input);
+                results.lock().unwrap_or_else(|e| e.into_inner())[idx] = Some(result);
+            });
+        }
+    });
+    results
+        .into_inner()
+        .unwrap_or_else(|e| e.into_inner())
+        .into_iter()
+        .map(|r| r.expect("every input is processed"))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn preserves_order() {
+        let inputs = (0..20).collect::<Vec<u64>>();
+        let outputs = run(inputs, 4, |i| {
+            // later inputs finish first
+            thread::sleep(Duration::from_millis(20 - i));
+            i * 2
+        });
+        assert_eq!(outputs, (0..20).map(|i| i * 2).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn limits_workers() {
+        let running = AtomicUsize::new(0);
+        let max = AtomicUsize::new(0);
+        run((0..16).collect::<Vec<_>>(), 3, |_| {
+            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+            max.fetch_max(now, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(10));
+            running.fetch_sub(1, Ordering::SeqCst);
+        });
+        assert!(max.load(Ordering::SeqCst) <= 3);
+    }
+}
@@ -0,0 +1,387 @@
+//! Contains a backend that caches the raw output of `ichiran-cli` calls.
+
+use crate::{version::PROBE_EXPR, Backend, Exchange, IchiranError};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// Prefix of the files in a disk store, so that the store can share a directory with other files.
+const FILE_PREFIX: &str = "ichiran-rs-";
+
+/// Name of the file in a disk store that contains the version the entries were cached with.
+const VERSION_FILE: &str = "ichiran-rs-version";
+
+type Key = (Vec<String>, String);
+
+/// A [`Backend`] that caches successful calls to another backend,
+/// keyed by the arguments (which determine the mode and limit) and the input.
+///
+/// The raw stdout and stderr of the calls are cached rather than the parsed results,
+/// so every [`crate::IchiranCli`] method benefits from the cache and cached output is parsed again on each call,
+/// which is cheap compared to running `ichiran-cli`.
+///
+/// Entries are kept in an in-memory LRU cache, and optionally in a directory on disk so that they persist between runs.
+/// Calls that evaluate Lisp with `-e` are never cached.
+///
+/// The cached entries are tied to the version of ichiran and its dictionary.
+/// Unless it has been set with [`CachingBackend::set_version`], the version is checked with [`CachingBackend::check_version`]
+/// on the first cache miss.
+#[derive(Debug)]
+pub struct CachingBackend<B> {
+    inner: B,
+    memory: Mutex<Lru>,
+    disk: Option<PathBuf>,
+    version: Mutex<Option<String>>,
+}
+
+impl<B: Backend> CachingBackend<B> {
+    /// Caches the calls made through `inner`, keeping up to `capacity` entries in memory.
+    pub fn new(inner: B, capacity: usize) -> Self {
+        Self {
+            inner,
+            memory: Mutex::new(Lru::new(capacity)),
+            disk: None,
+            version: Mutex::new(None),
+        }
+    }
+
+    /// Additionally stores the cached entries in the given directory, creating it if necessary.
+    /// The files of the store are prefixed with `ichiran-rs-`, and other files in the directory are left alone.
+    /// The version of ichiran is checked with [`CachingBackend::check_version`],
+    /// and entries stored by a different version are removed.
+    pub fn with_disk_store(mut self, dir: impl Into<PathBuf>) -> Result<Self, IchiranError> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir).map_err(IchiranError::CacheError)?;
+        self.disk = Some(dir);
+        self.check_version()?;
+        Ok(self)
+    }
+
+    /// Returns the backend calls are passed through to.
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+
+    /// Detects the version of ichiran and its dictionary through the inner backend with the same probe as [`crate::IchiranCli::probe`],
+    /// and clears the cache if it has changed since the entries were cached.
+    /// The dictionary size is part of the version, so reloading JMdict also invalidates the cache.
+    /// Returns the raw output of the probe.
+    pub fn check_version(&self) -> Result<String, IchiranError> {
+        let (stdout, _stderr) = self.inner.run(&["-e"], PROBE_EXPR)?;
+        let version = stdout.trim().to_string();
+        self.set_version(&version)?;
+        Ok(version)
+    }
+
+    /// Clears the cache if the given version differs from the one the entries were cached with.
+    pub fn set_version(&self, version: &str) -> Result<(), IchiranError> {
+        let mut current = lock(&self.version);
+        if let Some(dir) = &self.disk {
+            if current.is_none() {
+                *current = fs::read_to_string(dir.join(VERSION_FILE)).ok();
+            }
+        }
+        if current.as_deref() != Some(version) {
+            self.clear()?;
+            if let Some(dir) = &self.disk {
+                fs::write(dir.join(VERSION_FILE), version).map_err(IchiranError::CacheError)?;
+            }
+            *current = Some(version.to_string());
+        }
+        Ok(())
+    }
+
+    /// Removes all cached entries. Only the entry files of the disk store are removed from its directory.
+    pub fn clear(&self) -> Result<(), IchiranError> {
+        lock(&self.memory).clear();
+        if let Some(dir) = &self.disk {
+            for entry in fs::read_dir(dir).map_err(IchiranError::CacheError)? {
+                let entry = entry.map_err(IchiranError::CacheError)?;
+                if entry.file_name().to_str().map_or(false, is_entry_file) {
+                    fs::remove_file(entry.path()).map_err(IchiranError::CacheError)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn read_disk(&self, key: &Key) -> Option<(String, String)> {
+        let dir = self.disk.as_ref()?;
+        let contents = fs::read_to_string(entry_path(dir, key)).ok()?;
+        let exchange: Exchange = serde_json::from_str(&contents).ok()?;
+        // guards against hash collisions
+        if exchange.args == key.0 && exchange.input == key.1 {
+            Some((exchange.stdout, exchange.stderr))
+        } else {
+            None
+        }
+    }
+
+    fn write_disk(&self, key: &Key, output: &(String, String)) -> Result<(), IchiranError> {
+        let dir = match &self.disk {
+            Some(dir) => dir,
+            None => return Ok(()),
+        };
+        let exchange = Exchange {
+            args: key.0.clone(),
+            input: key.1.clone(),
+            stdout: output.0.clone(),
+            stderr: output.1.clone(),
+            success: true,
+        };
+        let contents =
+            serde_json::to_string(&exchange).expect("exchanges can always be serialized");
+        fs::write(entry_path(dir, key), contents).map_err(IchiranError::CacheError)
+    }
+}
+
+impl<B: Backend> Backend for CachingBackend<B> {
+    fn run(&self, args: &[&str], input: &str) -> Result<(String, String), IchiranError> {
+        if args.contains(&"-e") {
+            return self.inner.run(args, input);
+        }
+
+        let key = (
+            args.iter().map(|a| a.to_string()).collect::<Vec<_>>(),
+            input.to_string(),
+        );
+        if let Some(output) = lock(&self.memory).get(&key) {
+            return Ok(output);
+        }
+        if let Some(output) = self.read_disk(&key) {
+            lock(&self.memory).insert(key, output.clone());
+            return Ok(output);
+        }
+
+        if lock(&self.version).is_none() {
+            self.check_version()?;
+        }
+        let output = self.inner.run(args, input)?;
+        self.write_disk(&key, &output)?;
+        lock(&self.memory).insert(key, output.clone());
+        Ok(output)
+    }
+}
+
+/// A least recently used cache.
+#[derive(Debug)]
+struct Lru {
+    capacity: usize,
+    /// Incremented on every access, used to determine which entry was used least recently.
+    tick: u64,
+    entries: HashMap<Key, ((String, String), u64)>,
+    by_tick: BTreeMap<u64, Key>,
+}
+
+impl Lru {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            tick: 0,
+            entries: HashMap::new(),
+            by_tick: BTreeMap::new(),
+        }
+    }
+
+    fn get(&mut self, key: &Key) -> Option<(String, String)> {
+        self.tick += 1;
+        let (value, tick) = self.entries.get_mut(key)?;
+        self.by_tick.remove(tick);
+        *tick = self.tick;
+        self.by_tick.insert(self.tick, key.clone());
+        Some(value.clone())
+    }
+
+    fn insert(&mut self, key: Key, value: (String, String)) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.tick += 1;
+        if let Some((_, tick)) = self.entries.insert(key.clone(), (value, self.tick)) {
+            self.by_tick.remove(&tick);
+        }
+        self.by_tick.insert(self.tick, key);
+        while self.entries.len() > self.capacity {
+            let oldest = *self
+                .by_tick
+                .keys()
+                .next()
+                .expect("by_tick has an entry for each entry");
+            if let Some(key) = self.by_tick.remove(&oldest) {
+                self.entries.remove(&key);
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.by_tick.clear();
+    }
+}
+
+/// Returns the path of the file a key is stored in.
+/// Uses FNV-1a, since the hash needs to stay stable between runs.
+fn entry_path(dir: &Path, key: &Key) -> PathBuf {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for part in key.0.iter().chain(std::iter::once(&key.1)) {
+        for byte in part.bytes().chain(std::iter::once(0)) {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    dir.join(format!("{FILE_PREFIX}{hash:016x}.json"))
+}
+
+/// Returns true if the file name is one that [`entry_path`] creates.
+fn is_entry_file(name: &str) -> bool {
+    name.strip_prefix(FILE_PREFIX)
+        .and_then(|name| name.strip_suffix(".json"))
+        .map_or(false, |hash| {
+            hash.len() == 16 && hash.bytes().all(|b| b.is_ascii_hexdigit())
+        })
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::IchiranCli;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Returns the input as the output and counts the calls.
+    #[derive(Default)]
+    struct Counting {
+        calls: AtomicUsize,
+        probes: AtomicUsize,
+        version: Mutex<String>,
+    }
+
+    impl Backend for Counting {
+        fn run(&self, args: &[&str], input: &str) -> Result<(String, String), IchiranError> {
+            if args == ["-e"] {
+                self.probes.fetch_add(1, Ordering::SeqCst);
+                return Ok((format!("{}\n", lock(&self.version)), String::new()));
+            }
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok((format!("{}{input}\n", args.join(" ")), String::new()))
+        }
+    }
+
+    fn calls(backend: &CachingBackend<&Counting>) -> usize {
+        backend.inner().calls.load(Ordering::SeqCst)
+    }
+
+    #[test]
+    fn caches_in_memory() {
+        let counting = Counting::default();
+        let ichiran = IchiranCli::with_backend(CachingBackend::new(&counting, 2));
+        assert_eq!(ichiran.romanize("a").unwrap(), "a");
+        assert_eq!(ichiran.romanize("a").unwrap(), "a");
+        assert_eq!(calls(ichiran.backend()), 1);
+
+        // different modes and limits are cached separately
+        ichiran.backend().run(&["-i"], "a").unwrap();
+        ichiran.backend().run(&["-f", "-l", "2"], "a").unwrap();
+        assert_eq!(calls(ichiran.backend()), 3);
+
+        // "a" without flags was evicted as the least recently used entry
+        ichiran.romanize("a").unwrap();
+        assert_eq!(calls(ichiran.backend()), 4);
+        ichiran.backend().run(&["-f", "-l", "2"], "a").unwrap();
+        assert_eq!(calls(ichiran.backend()), 4);
+    }
+
+    #[test]
+    fn caches_on_disk() {
+        let dir = std::env::temp_dir().join(format!("ichiran-rs-cache-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let counting = Counting::default();
+        *lock(&counting.version) = "1".to_string();
+
+        let cache = CachingBackend::new(&counting, 0)
+            .with_disk_store(&dir)
+            .unwrap();
+        cache.run(&[], "a").unwrap();
+        cache.run(&[], "a").unwrap();
+        assert_eq!(calls(&cache), 1);
+
+        let cache = CachingBackend::new(&counting, 0)
+            .with_disk_store(&dir)
+            .unwrap();
+        assert_eq!(cache.run(&[], "a").unwrap().0, "a\n");
+        assert_eq!(calls(&cache), 1);
+
+        // a new version invalidates the stored entries
+        *lock(&counting.version) = "2".to_string();
+        let cache = CachingBackend::new(&counting, 0)
+            .with_disk_store(&dir)
+            .unwrap();
+        cache.run(&[], "a").unwrap();
+        assert_eq!(calls(&cache), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn checks_version_on_first_miss() {
+        let counting = Counting::default();
+        let cache = CachingBackend::new(&counting, 10);
+        assert_eq!(counting.probes.load(Ordering::SeqCst), 0);
+        cache.run(&[], "a").unwrap();
+        assert_eq!(counting.probes.load(Ordering::SeqCst), 1);
+        cache.run(&[], "a").unwrap();
+        cache.run(&[], "b").unwrap();
+        assert_eq!(counting.probes.load(Ordering::SeqCst), 1);
+        assert_eq!(calls(&cache), 2);
+
+        // a version that was set explicitly is not checked again
+        let cache = CachingBackend::new(&counting, 10);
+        cache.set_version("1").unwrap();
+        cache.run(&[], "a").unwrap();
+        assert_eq!(counting.probes.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn invalidates_on_version_change() {
+        let counting = Counting::default();
+        let cache = CachingBackend::new(&counting, 10);
+        cache.set_version("1").unwrap();
+        cache.run(&[], "a").unwrap();
+        cache.set_version("1").unwrap();
+        cache.run(&[], "a").unwrap();
+        assert_eq!(calls(&cache), 1);
+        cache.set_version("2").unwrap();
+        cache.run(&[], "a").unwrap();
+        assert_eq!(calls(&cache), 2);
+    }
+
+    #[test]
+    fn clears_only_entry_files() {
+        let dir = std::env::temp_dir().join(format!("ichiran-rs-shared-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("notes.json"), "{}").unwrap();
+        fs::write(dir.join("ichiran-rs-notes.json"), "{}").unwrap();
+        fs::write(dir.join("version"), "mine").unwrap();
+
+        let counting = Counting::default();
+        let cache = CachingBackend::new(&counting, 0)
+            .with_disk_store(&dir)
+            .unwrap();
+        cache.run(&[], "a").unwrap();
+        let entry = entry_path(&dir, &(vec![], "a".to_string()));
+        assert!(entry.exists());
+
+        cache.clear().unwrap();
+        assert!(!entry.exists());
+        assert!(dir.join("notes.json").exists());
+        assert!(dir.join("ichiran-rs-notes.json").exists());
+        assert_eq!(fs::read_to_string(dir.join("version")).unwrap(), "mine");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,256 @@
+//! Contains helpers for splitting long inputs into chunks and stitching the results back together.
+
+use crate::{RomanizedWithInfo, Segment};
+
+/// Characters that end a sentence or a quote, where the input is preferably split.
+const SENTENCE_ENDS: &[char] = &[
+    '。', '！', '？', '!', '?', '\n', '」', '』', '）', ')', '】',
+];
+
+/// Characters that are used to split sentences that do not fit in a single chunk.
+const CLAUSE_ENDS: &[char] = &['、', '，', ',', ' ', '　'];
+
+/// Splits the input into chunks of at most `max_len` bytes.
+/// The input is split after the last sentence boundary that fits in the chunk,
+/// falling back to clause boundaries and finally to any character boundary.
+/// Concatenating the chunks results in the original input.
+pub(crate) fn split(input: &str, max_len: usize) -> Vec<&str> {
+    let mut chunks = vec![];
+    let mut rest = input;
+    while rest.len() > max_len {
+        let mut window_end = max_len;
+        while !rest.is_char_boundary(window_end) {
+            window_end -= 1;
+        }
+        let window = &rest[..window_end];
+        let end = last_boundary(window, SENTENCE_ENDS)
+            .or_else(|| last_boundary(window, CLAUSE_ENDS))
+            .unwrap_or(window_end);
+        // always make progress, even if max_len is smaller than the first character
+        let end = if end == 0 {
+            rest.chars().next().map_or(rest.len(), char::len_utf8)
+        } else {
+            end
+        };
+        let (chunk, remaining) = rest.split_at(end);
+        chunks.push(chunk);
+        rest = remaining;
+    }
+    if !rest.is_empty() {
+        chunks.push(rest);
+    }
+    chunks
+}
+
+/// A part of a chunked input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Piece<'a, T = &'a str> {
+    /// Text that is passed to ichiran, or the result of passing it to ichiran.
+    Text(T),
+    /// Whitespace at the borders of a chunk, which is not significant to ichiran but is kept in the results.
+    Whitespace(&'a str),
+}
+
+impl<'a, T> Piece<'a, T> {
+    /// Applies the fallible function to the text.
+    pub(crate) fn try_map<U, E>(
+        self,
+        f: impl FnOnce(T) -> Result<U, E>,
+    ) -> Result<Piece<'a, U>, E> {
+        match self {
+            Self::Text(text) => f(text).map(Piece::Text),
+            Self::Whitespace(whitespace) => Ok(Piece::Whitespace(whitespace)),
+        }
+    }
+}
+
+/// Splits the input into chunks with [`split`] and separates the whitespace at the chunk borders from the text.
+pub(crate) fn pieces(input: &str, max_len: usize) -> Vec<Piece<'_>> {
+    let mut pieces = vec![];
+    for chunk in split(input, max_len) {
+        let text = chunk.trim();
+        if text.is_empty() {
+            pieces.push(Piece::Whitespace(chunk));
+            continue;
+        }
+        let start = chunk.len() - chunk.trim_start().len();
+        let end = start + text.len();
+        if start > 0 {
+            pieces.push(Piece::Whitespace(&chunk[..start]));
+        }
+        pieces.push(Piece::Text(text));
+        if end < chunk.len() {
+            pieces.push(Piece::Whitespace(&chunk[end..]));
+        }
+    }
+    pieces
+}
+
+/// Returns the byte index after the last boundary character in the text.
+fn last_boundary(text: &str, boundaries: &[char]) -> Option<usize> {
+    text.char_indices()
+        .rev()
+        .find(|(_, c)| boundaries.contains(c))
+        .map(|(idx, c)| idx + c.len_utf8())
+}
+
+/// Concatenates the segments of consecutive chunks, merging the non-word segments at the chunk borders.
+/// The whitespace between chunks is kept as [`Segment::Other`].
+pub(crate) fn stitch<'a>(
+    pieces: impl IntoIterator<Item = Piece<'a, Vec<Segment>>>,
+) -> Vec<Segment> {
+    let segments = pieces.into_iter().flat_map(|piece| match piece {
+        Piece::Text(segments) => segments,
+        Piece::Whitespace(whitespace) => vec![Segment::Other(whitespace.to_string())],
+    });
+    let mut stitched: Vec<Segment> = vec![];
+    for segment in segments {
+        match (stitched.last_mut(), segment) {
+            (Some(Segment::Other(last)), Segment::Other(other)) => last.push_str(&other),
+            (_, segment) => stitched.push(segment),
+        }
+    }
+    stitched
+}
+
+/// Joins the romanizations of consecutive chunks with the whitespace between them,
+/// or with a space if the chunks were not separated by whitespace.
+pub(crate) fn join<'a>(pieces: impl IntoIterator<Item = Piece<'a, String>>) -> String {
+    let mut joined = String::new();
+    let mut separator = String::new();
+    for piece in pieces {
+        match piece {
+            Piece::Text(text) => {
+                if !joined.is_empty() {
+                    joined.push_str(if separator.is_empty() {
+                        " "
+                    } else {
+                        &separator
+                    });
+                }
+                joined.push_str(&text);
+                separator.clear();
+            }
+            Piece::Whitespace(whitespace) => separator.push_str(whitespace),
+        }
+    }
+    joined
+}
+
+/// Joins the results of consecutive chunks like [`join`], concatenating the entries.
+pub(crate) fn join_with_info<'a>(
+    pieces: impl IntoIterator<Item = Piece<'a, RomanizedWithInfo>>,
+) -> RomanizedWithInfo {
+    let mut entries = vec![];
+    let pieces = pieces
+        .into_iter()
+        .map(|piece| match piece {
+            Piece::Text(info) => {
+                entries.extend(info.entries);
+                Piece::Text(info.romanized)
+            }
+            Piece::Whitespace(whitespace) => Piece::Whitespace(whitespace),
+        })
+        .collect::<Vec<_>>();
+    RomanizedWithInfo {
+        romanized: join(pieces),
+        entries,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn splits_at_sentences() {
+        let input = "一覧は最高だぞ。いい天気ですね！一人目";
+        assert_eq!(split(input, 100), [input]);
+        assert_eq!(
+            split(input, 50),
+            ["一覧は最高だぞ。いい天気ですね！", "一人目"]
+        );
+        assert_eq!(
+            split(input, 30),
+            ["一覧は最高だぞ。", "いい天気ですね！", "一人目"]
+        );
+    }
+
+    #[test]
+    fn splits_at_closing_brackets() {
+        let input = "「東京」に行きました。";
+        assert_eq!(split(input, 21), ["「東京」", "に行きました。"]);
+    }
+
+    #[test]
+    fn splits_long_sentences() {
+        let input = "今日は、天気がいい";
+        assert_eq!(split(input, 20), ["今日は、", "天気がいい"]);
+        assert_eq!(split(input, 7), ["今日", "は、", "天気", "がい", "い"]);
+        assert_eq!(
+            split(input, 1),
+            input.chars().map(String::from).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn separates_whitespace() {
+        let input = "一覧は最高だぞ。 いい天気ですね！\n一人目  ";
+        assert_eq!(
+            pieces(input, 30),
+            [
+                Piece::Text("一覧は最高だぞ。"),
+                Piece::Whitespace(" "),
+                Piece::Text("いい天気ですね！"),
+                Piece::Whitespace("\n"),
+                Piece::Text("一人目"),
+                Piece::Whitespace("  "),
+            ]
+        );
+        assert_eq!(
+            pieces(" \n ", 1),
+            [
+                Piece::Whitespace(" "),
+                Piece::Whitespace("\n"),
+                Piece::Whitespace(" ")
+            ]
+        );
+    }
+
+    #[test]
+    fn stitches_segments() {
+        let stitched = stitch([
+            Piece::Text(vec![Segment::Other("a".to_string())]),
+            Piece::Text(vec![]),
+            Piece::Whitespace(" "),
+            Piece::Text(vec![
+                Segment::Other("b".to_string()),
+                Segment::Segmentations(vec![]),
+            ]),
+            Piece::Whitespace("\n"),
+            Piece::Text(vec![Segment::Segmentations(vec![])]),
+        ]);
+        assert_eq!(
+            stitched,
+            [
+                Segment::Other("a b".to_string()),
+                Segment::Segmentations(vec![]),
+                Segment::Other("\n".to_string()),
+                Segment::Segmentations(vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn joins_romanizations() {
+        let joined = join([
+            Piece::Whitespace(" "),
+            Piece::Text("iitenki desu ne.".to_string()),
+            Piece::Text("hitorime".to_string()),
+            Piece::Whitespace("\n"),
+            Piece::Text("ichiran".to_string()),
+            Piece::Whitespace(" "),
+        ]);
+        assert_eq!(joined, "iitenki desu ne. hitorime\nichiran");
+    }
+}
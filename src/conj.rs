@@ -0,0 +1,188 @@
+//! Contains the conjugation types used by ichiran.
+
+use std::{convert::Infallible, fmt, str::FromStr};
+
+/// A conjugation type from ichiran's conjugation table, such as `Past (~ta)`.
+///
+/// Descriptions that are not recognised are kept as [`ConjugationType::Unknown`].
+/// With the `serde` feature, serialized as the description used by ichiran.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ConjugationType {
+    NonPast,
+    /// `~ta`
+    Past,
+    /// `~te`
+    Conjunctive,
+    /// `~eba`
+    Provisional,
+    Potential,
+    Passive,
+    Causative,
+    CausativePassive,
+    Volitional,
+    Imperative,
+    /// `~tara`
+    Conditional,
+    /// `~tari`
+    Alternative,
+    /// `~i`
+    Continuative,
+    /// `~ku` for i-adjectives.
+    Adverbial,
+    AdjectiveStem,
+    NegativeStem,
+    /// `~sasu`
+    CausativeSu,
+    /// Old or literary form.
+    Literary,
+    Unknown(String),
+}
+
+impl ConjugationType {
+    /// Returns the description used by ichiran.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::NonPast => "Non-past",
+            Self::Past => "Past (~ta)",
+            Self::Conjunctive => "Conjunctive (~te)",
+            Self::Provisional => "Provisional (~eba)",
+            Self::Potential => "Potential",
+            Self::Passive => "Passive",
+            Self::Causative => "Causative",
+            Self::CausativePassive => "Causative-Passive",
+            Self::Volitional => "Volitional",
+            Self::Imperative => "Imperative",
+            Self::Conditional => "Conditional (~tara)",
+            Self::Alternative => "Alternative (~tari)",
+            Self::Continuative => "Continuative (~i)",
+            Self::Adverbial => "Adverbial",
+            Self::AdjectiveStem => "Adjective Stem",
+            Self::NegativeStem => "Negative Stem",
+            Self::CausativeSu => "Causative (~sasu)",
+            Self::Literary => "Old/literary form",
+            Self::Unknown(description) => description,
+        }
+    }
+}
+
+impl From<&str> for ConjugationType {
+    fn from(s: &str) -> Self {
+        // the ending in parentheses is optional, except for the two kinds of causatives
+        let name = match s {
+            "Causative (~sasu)" => return Self::CausativeSu,
+            _ => s.split(" (").next().unwrap_or(s).trim(),
+        };
+        match name {
+            "Non-past" => Self::NonPast,
+            "Past" => Self::Past,
+            "Conjunctive" => Self::Conjunctive,
+            "Provisional" => Self::Provisional,
+            "Potential" => Self::Potential,
+            "Passive" => Self::Passive,
+            "Causative" => Self::Causative,
+            "Causative-Passive" => Self::CausativePassive,
+            "Volitional" => Self::Volitional,
+            "Imperative" => Self::Imperative,
+            "Conditional" => Self::Conditional,
+            "Alternative" => Self::Alternative,
+            "Continuative" => Self::Continuative,
+            "Adverbial" => Self::Adverbial,
+            "Adjective Stem" => Self::AdjectiveStem,
+            "Negative Stem" => Self::NegativeStem,
+            "Old/literary form" => Self::Literary,
+            _ => Self::Unknown(s.to_string()),
+        }
+    }
+}
+
+impl FromStr for ConjugationType {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::from(s))
+    }
+}
+
+impl fmt::Display for ConjugationType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for ConjugationType {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for ConjugationType {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Ok(Self::from(s.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn parses_descriptions() {
+        assert_eq!("Past (~ta)".parse(), Ok(ConjugationType::Past));
+        assert_eq!(
+            "Conjunctive (~te)".parse(),
+            Ok(ConjugationType::Conjunctive)
+        );
+        assert_eq!(
+            "Causative-Passive".parse(),
+            Ok(ConjugationType::CausativePassive)
+        );
+        assert_eq!("Causative".parse(), Ok(ConjugationType::Causative));
+        assert_eq!(
+            "Causative (~sasu)".parse(),
+            Ok(ConjugationType::CausativeSu)
+        );
+        assert_eq!(
+            "Something new".parse(),
+            Ok(ConjugationType::Unknown("Something new".to_string()))
+        );
+        assert_eq!(
+            ConjugationType::Continuative.to_string(),
+            "Continuative (~i)"
+        );
+    }
+
+    #[test]
+    fn classifies_conjugations() {
+        let segmented = testing::fixtures()
+            .segment("食べさせられなかった", None)
+            .unwrap();
+        let conj = &testing::word_info(&segmented, 0, 0).conj[0];
+        assert_eq!(conj.prop[0].conjugation_type(), Some(ConjugationType::Past));
+        assert_eq!(
+            conj.via[0].prop[0].conjugation_type(),
+            Some(ConjugationType::CausativePassive)
+        );
+        assert!(conj.is_past());
+        assert!(conj.is_negative());
+        assert!(!conj.is_formal());
+        assert!(conj.has_type(&ConjugationType::CausativePassive));
+        assert!(!conj.has_type(&ConjugationType::Passive));
+    }
+
+    #[test]
+    fn finds_types_in_nested_conjugations() {
+        // 食べさせられた as causative → passive → past
+        let segmented = testing::segment_with("食べさせられた", testing::NESTED_VIA);
+        let conj = &testing::word_info(&segmented, 0, 0).conj[0];
+        assert!(conj.has_type(&ConjugationType::Past));
+        assert!(conj.has_type(&ConjugationType::Passive));
+        assert!(conj.has_type(&ConjugationType::Causative));
+        assert!(!conj.has_type(&ConjugationType::CausativePassive));
+        assert!(conj.via[0].has_type(&ConjugationType::Causative));
+        assert!(!conj.via[0].has_type(&ConjugationType::Past));
+    }
+}
@@ -0,0 +1,178 @@
+//! Contains functionality for explaining how conjugated words were derived from their dictionary form.
+
+use crate::{reading, ConjProp, ConjugationType, Gloss, PartOfSpeech, Via, WordInfo};
+
+/// The steps that derive a conjugated word from its dictionary form,
+/// such as 食べる → causative-passive → negative past for 食べさせられなかった.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Deinflection {
+    /// The dictionary form text, if reported by ichiran.
+    pub base_text: Option<String>,
+    /// The dictionary form kana, if reported by ichiran.
+    pub base_kana: Option<String>,
+    /// The glosses of the dictionary form.
+    pub gloss: Vec<Gloss>,
+    /// The conjugation steps, ordered from the dictionary form to the final form.
+    pub steps: Vec<DeinflectionStep>,
+}
+
+/// A single conjugation applied to a word.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeinflectionStep {
+    /// The form produced by this step. ichiran only reports the final form, so this is `None` for intermediate steps.
+    pub form: Option<String>,
+    pub part_of_speech: PartOfSpeech,
+    /// `None` if ichiran did not report a conjugation type.
+    pub conjugation_type: Option<ConjugationType>,
+    pub formal: bool,
+    pub negative: bool,
+    /// The glosses ichiran reported for this level of the conjugation.
+    /// Usually only the step applied to the dictionary form has glosses, which are the glosses of the dictionary form.
+    pub gloss: Vec<Gloss>,
+}
+
+impl WordInfo {
+    /// Returns how the word was derived from its dictionary form, one [`Deinflection`] for each entry in [`WordInfo::conj`].
+    /// Empty for words that are not conjugated.
+    ///
+    /// Where ichiran reports several interpretations of the same conjugation level, the first one is used.
+    pub fn deinflections(&self) -> Vec<Deinflection> {
+        self.conj
+            .iter()
+            .map(|conj| {
+                let mut deinflection = match conj.via.first() {
+                    Some(via) => from_via(via),
+                    None => base(conj.reading.as_deref(), &conj.gloss),
+                };
+                deinflection.steps.extend(step(&conj.prop, &conj.gloss));
+                if let Some(last) = deinflection.steps.last_mut() {
+                    last.form = Some(self.text.clone());
+                }
+                deinflection
+            })
+            .collect()
+    }
+}
+
+/// Builds the deinflection up to and including the given level.
+fn from_via(via: &Via) -> Deinflection {
+    let mut deinflection = match via.via.first() {
+        Some(inner) => from_via(inner),
+        None => base(via.reading.as_deref(), &via.gloss),
+    };
+    deinflection.steps.extend(step(&via.prop, &via.gloss));
+    deinflection
+}
+
+/// Creates a deinflection with no steps from the reading and glosses of the dictionary form.
+fn base(reading: Option<&str>, gloss: &[Gloss]) -> Deinflection {
+    let (base_text, base_kana) = match reading.map(reading::split) {
+        Some((text, kana)) => (
+            Some(text.to_string()),
+            Some(kana.unwrap_or(text).to_string()),
+        ),
+        None => (None, None),
+    };
+    Deinflection {
+        base_text,
+        base_kana,
+        gloss: gloss.to_vec(),
+        steps: vec![],
+    }
+}
+
+fn step(prop: &[ConjProp], gloss: &[Gloss]) -> Option<DeinflectionStep> {
+    prop.first().map(|prop| DeinflectionStep {
+        form: None,
+        part_of_speech: prop.part_of_speech(),
+        conjugation_type: prop.conjugation_type(),
+        formal: prop.fml,
+        negative: prop.neg,
+        gloss: gloss.to_vec(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::{self, word_info};
+
+    #[test]
+    fn orders_steps_from_base() {
+        let segmented = testing::fixtures()
+            .segment("食べさせられなかった", None)
+            .unwrap();
+        let deinflections = word_info(&segmented, 0, 0).deinflections();
+        assert_eq!(deinflections.len(), 1);
+        let deinflection = &deinflections[0];
+        assert_eq!(deinflection.base_text.as_deref(), Some("食べる"));
+        assert_eq!(deinflection.base_kana.as_deref(), Some("たべる"));
+        assert_eq!(deinflection.gloss[0].gloss, "to eat");
+        assert_eq!(
+            deinflection.steps,
+            [
+                DeinflectionStep {
+                    form: None,
+                    part_of_speech: PartOfSpeech::V1,
+                    conjugation_type: Some(ConjugationType::CausativePassive),
+                    formal: false,
+                    negative: false,
+                    gloss: deinflection.gloss.clone(),
+                },
+                DeinflectionStep {
+                    form: Some("食べさせられなかった".to_string()),
+                    part_of_speech: PartOfSpeech::V1,
+                    conjugation_type: Some(ConjugationType::Past),
+                    formal: false,
+                    negative: true,
+                    gloss: vec![],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn handles_single_step() {
+        let cli = testing::fixtures();
+        let segmented = cli.segment("「東京」に行きました。", None).unwrap();
+        let deinflections = word_info(&segmented, 3, 1).deinflections();
+        assert_eq!(deinflections[0].base_text.as_deref(), Some("行く"));
+        assert_eq!(deinflections[0].steps.len(), 1);
+        assert!(deinflections[0].steps[0].formal);
+        assert_eq!(
+            deinflections[0].steps[0].form.as_deref(),
+            Some("行きました")
+        );
+
+        let segmented = cli.segment("一覧は最高だぞ", None).unwrap();
+        assert!(word_info(&segmented, 0, 0).deinflections().is_empty());
+    }
+
+    #[test]
+    fn follows_nested_derivations() {
+        let segmented = testing::segment_with("食べさせられた", testing::NESTED_VIA);
+        let deinflection = &word_info(&segmented, 0, 0).deinflections()[0];
+        assert_eq!(deinflection.base_text.as_deref(), Some("食べる"));
+        let types = deinflection
+            .steps
+            .iter()
+            .map(|s| s.conjugation_type.clone().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            types,
+            [
+                ConjugationType::Causative,
+                ConjugationType::Passive,
+                ConjugationType::Past
+            ]
+        );
+        let glosses = deinflection
+            .steps
+            .iter()
+            .map(|s| s.gloss.iter().map(|g| g.gloss.as_str()).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(glosses, [vec!["to eat"], vec![], vec![]]);
+    }
+}
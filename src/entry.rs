@@ -0,0 +1,386 @@
+//! Contains dictionary entries and functionality for fetching them from ichiran's database.
+
+use crate::{Backend, IchiranCli, IchiranError, PartOfSpeech};
+
+/// A complete JMdict entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DictionaryEntry {
+    pub seq: i32,
+    pub kanji: Vec<KanjiElement>,
+    pub kana: Vec<KanaElement>,
+    pub senses: Vec<EntrySense>,
+}
+
+/// A kanji form of an entry (`k_ele`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KanjiElement {
+    pub text: String,
+    /// Information about the form, such as `ateji` (`ke_inf`).
+    pub info: Vec<String>,
+    /// Priority tags such as `news1` or `ichi1` (`ke_pri`).
+    pub priority: Vec<String>,
+}
+
+/// A kana form of an entry (`r_ele`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KanaElement {
+    pub text: String,
+    /// True if the form is not a true reading of the kanji forms (`re_nokanji`).
+    pub no_kanji: bool,
+    /// The kanji forms this reading applies to, or empty if it applies to all of them (`re_restr`).
+    pub restrictions: Vec<String>,
+    /// Information about the form (`re_inf`).
+    pub info: Vec<String>,
+    /// Priority tags such as `news1` or `ichi1` (`re_pri`).
+    pub priority: Vec<String>,
+}
+
+/// A sense of an entry (`sense`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EntrySense {
+    /// The kanji forms this sense applies to, or empty if it applies to all of them (`stagk`).
+    pub kanji_restrictions: Vec<String>,
+    /// The kana forms this sense applies to, or empty if it applies to all of them (`stagr`).
+    pub kana_restrictions: Vec<String>,
+    /// If a sense has no part of speech tags, the tags of the previous sense apply, as specified by JMdict.
+    pub parts_of_speech: Vec<PartOfSpeech>,
+    /// Related entries, such as `一覧表` (`xref`).
+    pub cross_references: Vec<String>,
+    /// Entries with the opposite meaning (`ant`).
+    pub antonyms: Vec<String>,
+    /// Fields of application such as `comp` (`field`).
+    pub fields: Vec<String>,
+    /// Other information such as `uk` (`misc`).
+    pub misc: Vec<String>,
+    /// Additional information (`s_inf`).
+    pub info: Vec<String>,
+    /// Dialects such as `ksb` (`dial`).
+    pub dialects: Vec<String>,
+    pub glosses: Vec<String>,
+}
+
+impl<B: Backend> IchiranCli<B> {
+    /// Fetches the dictionary entry with the given JMdict sequence number from ichiran's database.
+    /// Returns `None` if there is no such entry.
+    ///
+    /// Conjugated forms generated by ichiran have their own sequence numbers, but are not JMdict entries.
+    pub fn entry(&self, seq: i32) -> Result<Option<DictionaryEntry>, IchiranError> {
+        let content = self.eval(&format!(
+            r#"(or (postmodern:query "SELECT content FROM entry WHERE seq = $1" {seq} :single) "")"#
+        ))?;
+        if content.trim().is_empty() {
+            return Ok(None);
+        }
+        parse_entry(&content)
+            .map(Some)
+            .ok_or(IchiranError::UnexpectedOutput(content))
+    }
+}
+
+/// Parses a JMdict `entry` element.
+fn parse_entry(xml: &str) -> Option<DictionaryEntry> {
+    let root = parse_xml(xml)?;
+    if root.name != "entry" {
+        return None;
+    }
+    let mut entry = DictionaryEntry {
+        seq: root.child_text("ent_seq")?.trim().parse().ok()?,
+        kanji: vec![],
+        kana: vec![],
+        senses: vec![],
+    };
+    for element in root.elements() {
+        match element.name.as_str() {
+            "k_ele" => entry.kanji.push(KanjiElement {
+                text: element.child_text("keb")?,
+                info: element.texts("ke_inf"),
+                priority: element.texts("ke_pri"),
+            }),
+            "r_ele" => entry.kana.push(KanaElement {
+                text: element.child_text("reb")?,
+                no_kanji: element.elements().any(|e| e.name == "re_nokanji"),
+                restrictions: element.texts("re_restr"),
+                info: element.texts("re_inf"),
+                priority: element.texts("re_pri"),
+            }),
+            "sense" => {
+                let mut parts_of_speech = element
+                    .texts("pos")
+                    .iter()
+                    .map(|tag| PartOfSpeech::from(tag.as_str()))
+                    .collect::<Vec<_>>();
+                if parts_of_speech.is_empty() {
+                    if let Some(previous) = entry.senses.last() {
+                        parts_of_speech = previous.parts_of_speech.clone();
+                    }
+                }
+                entry.senses.push(EntrySense {
+                    kanji_restrictions: element.texts("stagk"),
+                    kana_restrictions: element.texts("stagr"),
+                    parts_of_speech,
+                    cross_references: element.texts("xref"),
+                    antonyms: element.texts("ant"),
+                    fields: element.texts("field"),
+                    misc: element.texts("misc"),
+                    info: element.texts("s_inf"),
+                    dialects: element.texts("dial"),
+                    glosses: element.texts("gloss"),
+                })
+            }
+            _ => {}
+        }
+    }
+    Some(entry)
+}
+
+#[derive(Debug)]
+struct Element {
+    name: String,
+    children: Vec<Node>,
+}
+
+#[derive(Debug)]
+enum Node {
+    Element(Element),
+    Text(String),
+}
+
+impl Element {
+    fn elements(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter_map(|child| match child {
+            Node::Element(element) => Some(element),
+            Node::Text(_) => None,
+        })
+    }
+
+    fn text(&self) -> String {
+        self.children
+            .iter()
+            .filter_map(|child| match child {
+                Node::Text(text) => Some(text.as_str()),
+                Node::Element(_) => None,
+            })
+            .collect()
+    }
+
+    fn child_text(&self, name: &str) -> Option<String> {
+        self.elements().find(|e| e.name == name).map(Element::text)
+    }
+
+    fn texts(&self, name: &str) -> Vec<String> {
+        self.elements()
+            .filter(|e| e.name == name)
+            .map(Element::text)
+            .collect()
+    }
+}
+
+/// Parses the subset of XML used by JMdict entries. Attributes are ignored.
+fn parse_xml(xml: &str) -> Option<Element> {
+    let mut stack = vec![Element {
+        name: String::new(),
+        children: vec![],
+    }];
+    let mut rest = xml;
+    while !rest.is_empty() {
+        if let Some(tag) = rest.strip_prefix('<') {
+            if let Some(comment) = tag.strip_prefix("!--") {
+                rest = &comment[comment.find("-->")? + 3..];
+                continue;
+            }
+            let end = tag.find('>')?;
+            let (tag, after) = (&tag[..end], &tag[end + 1..]);
+            rest = after;
+            if tag.starts_with('?') || tag.starts_with('!') {
+                continue;
+            }
+            if let Some(name) = tag.strip_prefix('/') {
+                let element = stack.pop()?;
+                if element.name != name.trim() {
+                    return None;
+                }
+                stack.last_mut()?.children.push(Node::Element(element));
+                continue;
+            }
+            let (tag, empty) = match tag.strip_suffix('/') {
+                Some(tag) => (tag, true),
+                None => (tag, false),
+            };
+            let name = tag.split_whitespace().next()?.to_string();
+            let element = Element {
+                name,
+                children: vec![],
+            };
+            if empty {
+                stack.last_mut()?.children.push(Node::Element(element));
+            } else {
+                stack.push(element);
+            }
+        } else {
+            let end = rest.find('<').unwrap_or(rest.len());
+            let text = unescape(&rest[..end])?;
+            rest = &rest[end..];
+            if !text.trim().is_empty() {
+                stack.last_mut()?.children.push(Node::Text(text));
+            }
+        }
+    }
+    let mut document = stack.pop()?;
+    if !stack.is_empty() {
+        return None;
+    }
+    document.children.retain(|c| matches!(c, Node::Element(_)));
+    match document.children.pop()? {
+        Node::Element(root) => Some(root),
+        Node::Text(_) => None,
+    }
+}
+
+/// Decodes character references and the predefined entities.
+/// Other entities, such as JMdict's part of speech entities like `&n;`, are replaced with their name.
+fn unescape(text: &str) -> Option<String> {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        let end = rest[start..].find(';')? + start;
+        let entity = &rest[start + 1..end];
+        match entity {
+            "amp" => unescaped.push('&'),
+            "lt" => unescaped.push('<'),
+            "gt" => unescaped.push('>'),
+            "quot" => unescaped.push('"'),
+            "apos" => unescaped.push('\''),
+            _ => match entity.strip_prefix('#') {
+                Some(code) => {
+                    let code = match code.strip_prefix('x') {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                        None => code.parse().ok()?,
+                    };
+                    unescaped.push(char::from_u32(code)?);
+                }
+                None => unescaped.push_str(entity),
+            },
+        }
+        rest = &rest[end + 1..];
+    }
+    unescaped.push_str(rest);
+    Some(unescaped)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::FixtureBackend;
+
+    const QUERY: &str =
+        r#"(or (postmodern:query "SELECT content FROM entry WHERE seq = $1" 1160790 :single) "")"#;
+
+    const ICHIRAN: &str = r#"<entry>
+<ent_seq>1160790</ent_seq>
+<k_ele>
+<keb>一覧</keb>
+<ke_pri>ichi1</ke_pri>
+<ke_pri>news1</ke_pri>
+</k_ele>
+<r_ele>
+<reb>いちらん</reb>
+<re_pri>ichi1</re_pri>
+</r_ele>
+<sense>
+<pos>&n;</pos>
+<pos>&vs;</pos>
+<pos>&vt;</pos>
+<xref>一覧表</xref>
+<gloss>look</gloss>
+<gloss>glance</gloss>
+</sense>
+<sense>
+<gloss>summary</gloss>
+<gloss>list &amp; table</gloss>
+</sense>
+<!-- a comment -->
+<sense>
+<pos>n</pos>
+<misc>&uk;</misc>
+<s_inf>rare</s_inf>
+<gloss xml:lang="eng">catalog</gloss>
+</sense>
+</entry>
+"#;
+
+    #[test]
+    fn fetches_entries() {
+        let mut backend = FixtureBackend::new();
+        backend.insert(&["-e"], QUERY, ICHIRAN);
+        let cli = IchiranCli::with_backend(backend);
+        let entry = cli.entry(1160790).unwrap().unwrap();
+        assert_eq!(entry.seq, 1160790);
+        assert_eq!(
+            entry.kanji,
+            [KanjiElement {
+                text: "一覧".to_string(),
+                info: vec![],
+                priority: vec!["ichi1".to_string(), "news1".to_string()],
+            }]
+        );
+        assert_eq!(entry.kana[0].text, "いちらん");
+        assert!(!entry.kana[0].no_kanji);
+        assert_eq!(entry.senses.len(), 3);
+        assert_eq!(
+            entry.senses[0].parts_of_speech,
+            [PartOfSpeech::N, PartOfSpeech::Vs, PartOfSpeech::Vt]
+        );
+        assert_eq!(entry.senses[0].cross_references, ["一覧表"]);
+        assert_eq!(entry.senses[0].glosses, ["look", "glance"]);
+        // inherited from the previous sense
+        assert_eq!(
+            entry.senses[1].parts_of_speech,
+            entry.senses[0].parts_of_speech
+        );
+        assert_eq!(entry.senses[1].glosses, ["summary", "list & table"]);
+        assert_eq!(entry.senses[2].parts_of_speech, [PartOfSpeech::N]);
+        assert_eq!(entry.senses[2].misc, ["uk"]);
+        assert_eq!(entry.senses[2].info, ["rare"]);
+        assert_eq!(entry.senses[2].glosses, ["catalog"]);
+    }
+
+    #[test]
+    fn handles_missing_entries() {
+        let mut backend = FixtureBackend::new();
+        backend.insert(&["-e"], QUERY, "\n");
+        let cli = IchiranCli::with_backend(backend);
+        assert_eq!(cli.entry(1160790).unwrap(), None);
+
+        let mut backend = FixtureBackend::new();
+        backend.insert(&["-e"], QUERY, "<entry><ent_seq>1</ent_seq>");
+        let cli = IchiranCli::with_backend(backend);
+        assert!(matches!(
+            cli.entry(1160790),
+            Err(IchiranError::UnexpectedOutput(_))
+        ));
+    }
+
+    #[test]
+    fn parses_kana_elements() {
+        let entry = parse_entry(
+            "<entry><ent_seq>1</ent_seq><r_ele><reb>あ</reb><re_nokanji/><re_restr>亜</re_restr><re_inf>&ik;</re_inf></r_ele></entry>",
+        )
+        .unwrap();
+        assert_eq!(
+            entry.kana,
+            [KanaElement {
+                text: "あ".to_string(),
+                no_kanji: true,
+                restrictions: vec!["亜".to_string()],
+                info: vec!["ik".to_string()],
+                priority: vec![],
+            }]
+        );
+        assert_eq!(unescape("&#x4E00;&#20108;").unwrap(), "一二");
+    }
+}
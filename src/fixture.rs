@@ -0,0 +1,78 @@
+//! Contains a backend that serves recorded `ichiran-cli` output, for testing without an Ichiran installation.
+
+use crate::{Backend, IchiranError};
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// A [`Backend`] that returns recorded stdout for known `(args, input)` pairs instead of calling `ichiran-cli`.
+/// Calls that have no recorded output return [`IchiranError::NotRecorded`].
+#[derive(Debug, Clone, Default)]
+pub struct FixtureBackend {
+    fixtures: HashMap<(Vec<String>, String), String>,
+}
+
+/// An entry in a `fixtures.json` manifest.
+#[derive(Debug, Deserialize)]
+struct Fixture {
+    args: Vec<String>,
+    input: String,
+    /// Path to a file containing the stdout, relative to the manifest.
+    stdout: PathBuf,
+}
+
+impl FixtureBackend {
+    /// Creates a backend without any fixtures.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads the fixtures listed in `fixtures.json` in the given directory.
+    /// The manifest is a JSON array of objects with the fields `args`, `input` and `stdout`,
+    /// where `stdout` is the path to a file containing the output, relative to the directory.
+    /// ```json
+    /// [
+    ///   {"args": ["-f"], "input": "一人目", "stdout": "full/hitorime.json"},
+    ///   {"args": ["-f", "-l", "2"], "input": "一人目", "stdout": "full/hitorime-limit-2.json"}
+    /// ]
+    /// ```
+    pub fn from_dir(dir: impl AsRef<Path>) -> io::Result<Self> {
+        let dir = dir.as_ref();
+        let manifest = fs::read_to_string(dir.join("fixtures.json"))?;
+        let manifest: Vec<Fixture> = serde_json::from_str(&manifest)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let mut fixtures = HashMap::new();
+        for fixture in manifest {
+            let stdout = fs::read_to_string(dir.join(&fixture.stdout))?;
+            fixtures.insert((fixture.args, fixture.input), stdout);
+        }
+        Ok(Self { fixtures })
+    }
+
+    /// Adds a fixture, replacing any previous stdout recorded for the same arguments and input.
+    pub fn insert(&mut self, args: &[&str], input: &str, stdout: impl Into<String>) {
+        self.fixtures.insert(key(args, input), stdout.into());
+    }
+}
+
+impl Backend for FixtureBackend {
+    fn run(&self, args: &[&str], input: &str) -> Result<(String, String), IchiranError> {
+        match self.fixtures.get(&key(args, input)) {
+            Some(stdout) => Ok((stdout.clone(), String::new())),
+            None => Err(IchiranError::NotRecorded {
+                args: args.iter().map(|a| a.to_string()).collect(),
+                input: input.to_string(),
+            }),
+        }
+    }
+}
+
+fn key(args: &[&str], input: &str) -> (Vec<String>, String) {
+    (
+        args.iter().map(|a| a.to_string()).collect(),
+        input.to_string(),
+    )
+}
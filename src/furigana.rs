@@ -0,0 +1,256 @@
+//! Contains functionality for aligning the kana reading of a word with its kanji.
+
+use crate::{CompoundWordInfo, WordInfo};
+
+/// A run of text in a word, with the kana reading of the run if it is not written in kana.
+/// For example, 食べさせる is split into 食 with the reading た, and べさせる without a reading.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Furigana {
+    pub text: String,
+    /// `None` for runs written in kana.
+    pub reading: Option<String>,
+}
+
+impl WordInfo {
+    /// Aligns [`WordInfo::kana`] with [`WordInfo::text`]. See [`align`].
+    pub fn furigana(&self) -> Vec<Furigana> {
+        align(&self.text, &self.kana)
+    }
+}
+
+impl CompoundWordInfo {
+    /// Aligns [`CompoundWordInfo::kana`] with [`CompoundWordInfo::text`]. See [`align`].
+    pub fn furigana(&self) -> Vec<Furigana> {
+        align(&self.text, &self.kana)
+    }
+}
+
+/// Splits the text into runs of kana and runs of other characters such as kanji, 々 and digits,
+/// and assigns each run of other characters the part of the kana reading it corresponds to.
+/// Katakana and hiragana are treated as equal when matching kana runs against the reading.
+///
+/// If the alignment is ambiguous or impossible, kana at the start and end of the text that match the reading
+/// are kept as their own runs and the rest of the text is given the rest of the reading as a single run.
+pub fn align(text: &str, kana: &str) -> Vec<Furigana> {
+    let runs = runs(text);
+    let reading = kana.chars().collect::<Vec<_>>();
+    let mut solutions = vec![];
+    solve(&runs, &reading, 0, &mut vec![], &mut solutions);
+    match solutions.as_slice() {
+        [lens] => {
+            let mut pos = 0;
+            runs.iter()
+                .zip(lens)
+                .map(|(run, len)| {
+                    let reading = (!run.is_kana).then(|| reading[pos..pos + len].iter().collect());
+                    pos += len;
+                    Furigana {
+                        text: run.chars.iter().collect(),
+                        reading,
+                    }
+                })
+                .collect()
+        }
+        _ => fallback(text, &reading),
+    }
+}
+
+struct Run {
+    chars: Vec<char>,
+    is_kana: bool,
+}
+
+fn runs(text: &str) -> Vec<Run> {
+    let mut runs: Vec<Run> = vec![];
+    for c in text.chars() {
+        let is_kana = is_kana(c);
+        match runs.last_mut() {
+            Some(run) if run.is_kana == is_kana => run.chars.push(c),
+            _ => runs.push(Run {
+                chars: vec![c],
+                is_kana,
+            }),
+        }
+    }
+    runs
+}
+
+/// Finds the number of reading characters that each run corresponds to, stopping after two solutions.
+fn solve(
+    runs: &[Run],
+    reading: &[char],
+    pos: usize,
+    lens: &mut Vec<usize>,
+    solutions: &mut Vec<Vec<usize>>,
+) {
+    if solutions.len() > 1 {
+        return;
+    }
+    let run = match runs.get(lens.len()) {
+        Some(run) => run,
+        None => {
+            if pos == reading.len() {
+                solutions.push(lens.clone());
+            }
+            return;
+        }
+    };
+    let rest = &reading[pos..];
+    if run.is_kana {
+        if starts_with(rest, &run.chars) {
+            lens.push(run.chars.len());
+            solve(runs, reading, pos + run.chars.len(), lens, solutions);
+            lens.pop();
+        }
+    } else {
+        // every later run needs at least one character of the reading per character of kana, or one per run of kanji
+        let needed = runs[lens.len() + 1..]
+            .iter()
+            .map(|r| if r.is_kana { r.chars.len() } else { 1 })
+            .sum::<usize>();
+        for len in 1..=rest.len().saturating_sub(needed) {
+            lens.push(len);
+            solve(runs, reading, pos + len, lens, solutions);
+            lens.pop();
+        }
+    }
+}
+
+fn fallback(text: &str, reading: &[char]) -> Vec<Furigana> {
+    let text = text.chars().collect::<Vec<_>>();
+    let prefix = text
+        .iter()
+        .zip(reading)
+        .take_while(|(t, r)| is_kana(**t) && normalize(**t) == normalize(**r))
+        .count();
+    let suffix = text[prefix..]
+        .iter()
+        .rev()
+        .zip(reading[prefix..].iter().rev())
+        .take_while(|(t, r)| is_kana(**t) && normalize(**t) == normalize(**r))
+        .count();
+
+    let mut furigana = vec![];
+    if prefix > 0 {
+        furigana.push(Furigana {
+            text: text[..prefix].iter().collect(),
+            reading: None,
+        });
+    }
+    let middle = &text[prefix..text.len() - suffix];
+    if !middle.is_empty() {
+        furigana.push(Furigana {
+            text: middle.iter().collect(),
+            reading: Some(reading[prefix..reading.len() - suffix].iter().collect()),
+        });
+    }
+    if suffix > 0 {
+        furigana.push(Furigana {
+            text: text[text.len() - suffix..].iter().collect(),
+            reading: None,
+        });
+    }
+    furigana
+}
+
+fn starts_with(reading: &[char], kana: &[char]) -> bool {
+    reading.len() >= kana.len()
+        && reading
+            .iter()
+            .zip(kana)
+            .all(|(r, k)| normalize(*r) == normalize(*k))
+}
+
+/// Returns true for hiragana, katakana and the long vowel mark.
+/// ヶ and ヵ are excluded, as they are read like kanji in words such as 一ヶ月.
+fn is_kana(c: char) -> bool {
+    matches!(c, 'ぁ'..='ゖ' | 'ゝ' | 'ゞ' | 'ァ'..='ヴ' | 'ー' | 'ヽ' | 'ヾ')
+}
+
+/// Converts katakana to hiragana.
+fn normalize(c: char) -> char {
+    match c {
+        'ァ'..='ヶ' => char::from_u32(c as u32 - 0x60).unwrap_or(c),
+        _ => c,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing;
+
+    fn pairs(furigana: &[Furigana]) -> Vec<(&str, Option<&str>)> {
+        furigana
+            .iter()
+            .map(|f| (f.text.as_str(), f.reading.as_deref()))
+            .collect()
+    }
+
+    #[test]
+    fn aligns_okurigana() {
+        assert_eq!(
+            pairs(&align("食べさせる", "たべさせる")),
+            [("食", Some("た")), ("べさせる", None)]
+        );
+        assert_eq!(
+            pairs(&align("見た目", "みため")),
+            [("見", Some("み")), ("た", None), ("目", Some("め"))]
+        );
+        assert_eq!(
+            pairs(&align("お茶", "おちゃ")),
+            [("お", None), ("茶", Some("ちゃ"))]
+        );
+    }
+
+    #[test]
+    fn aligns_repetition_and_counters() {
+        assert_eq!(
+            pairs(&align("人々", "ひとびと")),
+            [("人々", Some("ひとびと"))]
+        );
+        assert_eq!(
+            pairs(&align("一ヶ月", "いっかげつ")),
+            [("一ヶ月", Some("いっかげつ"))]
+        );
+        assert_eq!(
+            pairs(&align("3人目", "さんにんめ")),
+            [("3人目", Some("さんにんめ"))]
+        );
+    }
+
+    #[test]
+    fn aligns_kana() {
+        assert_eq!(pairs(&align("テスト", "テスト")), [("テスト", None)]);
+        assert_eq!(pairs(&align("テスト", "てすと")), [("テスト", None)]);
+    }
+
+    #[test]
+    fn falls_back_when_ambiguous() {
+        // 日 could be read as either ひ or ひのひ
+        assert_eq!(
+            pairs(&align("お日の日だ", "おひのひのひだ")),
+            [("お", None), ("日の日", Some("ひのひのひ")), ("だ", None)]
+        );
+        // the reading does not match the kana in the text
+        assert_eq!(
+            pairs(&align("今日は", "きょうわ")),
+            [("今日は", Some("きょうわ"))]
+        );
+    }
+
+    #[test]
+    fn aligns_words() {
+        let segmented = testing::fixtures().segment("食べたい", None).unwrap();
+        let info = testing::compound_word_info(&segmented, 0, 0);
+        assert_eq!(
+            pairs(&info.furigana()),
+            [("食", Some("た")), ("べたい", None)]
+        );
+        assert_eq!(
+            pairs(&info.components[0].furigana()),
+            [("食", Some("た")), ("べ", None)]
+        );
+    }
+}
@@ -0,0 +1,176 @@
+//! Contains functionality for rendering segmented text as HTML.
+
+use crate::{furigana, offsets, Alternative, Segment, Word};
+use std::fmt::Write;
+
+/// Renders the segmented input as HTML, with `<ruby>` annotations over the parts of each word that are not written in kana.
+///
+/// Each word is wrapped in a `<span class="word">` with the following attributes, taken from the first segmentation and the first alternative:
+/// - `data-seq`: the sequence number reported by ichiran, or the space separated sequence numbers of the components of compound words.
+/// - `data-romanized`: the romanization of the word.
+/// - `data-score`: the score of the word.
+///
+/// The text of each word is copied from the input, or taken from ichiran's output if the word could not be found in the input.
+/// [`Segment::Other`] is rendered as reported by ichiran, which romanizes punctuation and normalizes whitespace,
+/// so for example `。` is rendered as `. `.
+/// All text is escaped.
+pub fn render(input: &str, segments: &[Segment]) -> String {
+    let spans = offsets::spans(input, segments);
+    let mut html = String::with_capacity(input.len() * 4);
+    for (segment, spans) in segments.iter().zip(&spans) {
+        let words = match segment {
+            Segment::Segmentations(segmentations) => segmentations
+                .first()
+                .map(|s| s.words.as_slice())
+                .unwrap_or_default(),
+            Segment::Other(text) => {
+                escape(&mut html, text);
+                continue;
+            }
+        };
+        let spans = spans.words.first().map(Vec::as_slice).unwrap_or_default();
+        for (idx, word) in words.iter().enumerate() {
+            let text = match spans.get(idx).and_then(Option::as_ref) {
+                Some(span) => span.text(input),
+                None => match word.alternatives.first() {
+                    Some(Alternative::WordInfo(info)) => &info.text,
+                    Some(Alternative::CompoundWordInfo(info)) => &info.text,
+                    None => continue,
+                },
+            };
+            render_word(&mut html, word, text);
+        }
+    }
+    html
+}
+
+fn render_word(html: &mut String, word: &Word, text: &str) {
+    html.push_str(r#"<span class="word""#);
+    let alternative = word.alternatives.first();
+    let (kana, seq, score) = match alternative {
+        Some(Alternative::WordInfo(info)) => (
+            info.kana.as_str(),
+            info.seq.map(|seq| seq.to_string()),
+            Some(info.score),
+        ),
+        Some(Alternative::CompoundWordInfo(info)) => {
+            let seqs = info
+                .components
+                .iter()
+                .filter_map(|c| c.seq)
+                .map(|seq| seq.to_string())
+                .collect::<Vec<_>>();
+            (
+                info.kana.as_str(),
+                (!seqs.is_empty()).then(|| seqs.join(" ")),
+                Some(info.score),
+            )
+        }
+        None => (text, None, None),
+    };
+    if let Some(seq) = seq {
+        attribute(html, "data-seq", &seq);
+    }
+    attribute(html, "data-romanized", &word.romanized);
+    if let Some(score) = score {
+        attribute(html, "data-score", &score.to_string());
+    }
+    html.push('>');
+    for run in furigana::align(text, kana) {
+        match run.reading {
+            Some(reading) => {
+                html.push_str("<ruby>");
+                escape(html, &run.text);
+                html.push_str("<rt>");
+                escape(html, &reading);
+                html.push_str("</rt></ruby>");
+            }
+            None => escape(html, &run.text),
+        }
+    }
+    html.push_str("</span>");
+}
+
+fn attribute(html: &mut String, name: &str, value: &str) {
+    let _ = write!(html, r#" {name}=""#);
+    escape(html, value);
+    html.push('"');
+}
+
+fn escape(html: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            '\'' => html.push_str("&#39;"),
+            _ => html.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::fixtures;
+
+    #[test]
+    fn renders_ruby() {
+        let input = "いい天気ですね。";
+        let segmented = fixtures().segment(input, None).unwrap();
+        assert_eq!(
+            render(input, &segmented),
+            concat!(
+                r#"<span class="word" data-seq="1914340" data-romanized="iitenki" data-score="315">いい<ruby>天気<rt>てんき</rt></ruby></span>"#,
+                r#"<span class="word" data-seq="1628500" data-romanized="desu" data-score="64">です</span>"#,
+                r#"<span class="word" data-seq="2029080" data-romanized="ne" data-score="16">ね</span>"#,
+                ". ",
+            )
+        );
+    }
+
+    #[test]
+    fn renders_compounds_and_other_text() {
+        let input = "「東京」に行きました。";
+        let segmented = fixtures().segment(input, None).unwrap();
+        let html = render(input, &segmented);
+        assert!(html.starts_with(r#" &quot;<span class="word" data-seq="#));
+        assert!(html.contains(r#"<ruby>東京<rt>とうきょう</rt></ruby></span>&quot; "#));
+        assert!(html.ends_with(r#"<ruby>行<rt>い</rt></ruby>きました</span>. "#));
+
+        let input = "食べたい";
+        let segmented = fixtures().segment(input, None).unwrap();
+        assert_eq!(
+            render(input, &segmented),
+            r#"<span class="word" data-seq="10073540 2017560" data-romanized="tabetai" data-score="390"><ruby>食<rt>た</rt></ruby>べたい</span>"#
+        );
+    }
+
+    #[test]
+    fn renders_other_segments_instead_of_the_input() {
+        let segmented = fixtures().segment("いい天気ですね。", None).unwrap();
+        // the input differs from the text ichiran segmented in the whitespace and punctuation around the words
+        let html = render("いい天気ですね　 。\n", &segmented);
+        assert!(html.ends_with(r#"data-score="16">ね</span>. "#), "{html}");
+
+        // words that are not in the input are taken from the segments
+        let html = render("", &segmented);
+        assert!(
+            html.contains(r#"いい<ruby>天気<rt>てんき</rt></ruby></span>"#),
+            "{html}"
+        );
+        assert!(html.ends_with("ね</span>. "), "{html}");
+    }
+
+    #[test]
+    fn escapes_text() {
+        let mut html = String::new();
+        escape(&mut html, r#"<a href="x">&'</a>"#);
+        assert_eq!(html, "&lt;a href=&quot;x&quot;&gt;&amp;&#39;&lt;/a&gt;");
+        assert_eq!(
+            render("<b>", &[Segment::Other("<b>".to_string())]),
+            "&lt;b&gt;"
+        );
+    }
+}
@@ -0,0 +1,318 @@
+//! Contains structured versions of the entries returned by `ichiran-cli -i`.
+
+use crate::{reading, ConjugationType, IchiranError, PartOfSpeech, RomanizedWithInfoEntry};
+
+/// The parsed contents of a [`RomanizedWithInfoEntry`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WordDetails {
+    pub romanized: String,
+    pub text: String,
+    /// Missing if the text is already written in kana.
+    pub kana: Option<String>,
+    pub senses: Vec<Sense>,
+    pub conjugations: Vec<ConjugationInfo>,
+    /// Lines that were not recognised as senses or conjugations.
+    pub other: Vec<String>,
+}
+
+/// A numbered sense such as `1. [n,vs,vt] 《info》 look; glance`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Sense {
+    pub number: u32,
+    /// Part of speech tags such as `n` or `adj-na`.
+    pub pos: Vec<String>,
+    /// Annotation in 《double angle brackets》.
+    pub info: Option<String>,
+    pub gloss: String,
+}
+
+/// A conjugation block such as `[ Conjugation: [v1] Past (~ta) Negative Plain`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConjugationInfo {
+    pub pos: String,
+    /// For example `Past (~ta)`, or `None` if ichiran printed `NIL`.
+    pub conj_type: Option<String>,
+    pub negative: bool,
+    pub formal: bool,
+    /// The conjugation this one was applied to, if the word was conjugated more than once.
+    pub via: Option<Box<ConjugationInfo>>,
+    /// The base form, only present on the innermost conjugation.
+    pub base: Option<BaseForm>,
+}
+
+/// The unconjugated form of a word, such as `食べる 【たべる】 : to eat`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BaseForm {
+    pub text: String,
+    pub kana: Option<String>,
+    pub gloss: String,
+}
+
+impl Sense {
+    /// Parses [`Sense::pos`].
+    pub fn parts_of_speech(&self) -> Vec<PartOfSpeech> {
+        self.pos
+            .iter()
+            .map(|tag| PartOfSpeech::from(tag.as_str()))
+            .collect()
+    }
+}
+
+impl ConjugationInfo {
+    /// Parses [`ConjugationInfo::conj_type`].
+    pub fn conjugation_type(&self) -> Option<ConjugationType> {
+        self.conj_type.as_deref().map(ConjugationType::from)
+    }
+}
+
+impl RomanizedWithInfoEntry {
+    /// Parses the raw lines of the entry.
+    pub fn parse(&self) -> Result<WordDetails, IchiranError> {
+        let (romanized, written) = self
+            .word
+            .strip_prefix("* ")
+            .and_then(|w| w.split_once("  "))
+            .ok_or_else(|| IchiranError::UnexpectedOutput(self.word.clone()))?;
+        let (text, kana) = split_kana(written);
+        let mut details = WordDetails {
+            romanized: romanized.to_string(),
+            text,
+            kana,
+            senses: vec![],
+            conjugations: vec![],
+            other: vec![],
+        };
+
+        // conjugation blocks that have not been closed yet, from the outermost
+        let mut open: Vec<ConjugationInfo> = vec![];
+        for line in &self.alternatives {
+            let (content, closed) = strip_closing_brackets(line.trim());
+            if let Some(header) = content.strip_prefix("[ Conjugation: ") {
+                open.push(
+                    parse_conjugation(header)
+                        .ok_or_else(|| IchiranError::UnexpectedOutput(line.clone()))?,
+                );
+            } else if let Some(conj) = open.last_mut() {
+                conj.base = Some(parse_base_form(content));
+            } else if let Some(sense) = parse_sense(content) {
+                details.senses.push(sense);
+            } else {
+                details.other.push(line.clone());
+            }
+            for _ in 0..closed {
+                close(&mut open, &mut details.conjugations);
+            }
+        }
+        // blocks that were never closed
+        while !open.is_empty() {
+            close(&mut open, &mut details.conjugations);
+        }
+        Ok(details)
+    }
+}
+
+/// Closes the innermost open conjugation block.
+fn close(open: &mut Vec<ConjugationInfo>, conjugations: &mut Vec<ConjugationInfo>) {
+    if let Some(conj) = open.pop() {
+        match open.last_mut() {
+            Some(parent) => parent.via = Some(Box::new(conj)),
+            None => conjugations.push(conj),
+        }
+    }
+}
+
+/// Strips the ` ]` markers that close conjugation blocks and returns how many there were.
+fn strip_closing_brackets(mut line: &str) -> (&str, usize) {
+    let mut closed = 0;
+    while let Some(stripped) = line.strip_suffix(" ]") {
+        line = stripped;
+        closed += 1;
+    }
+    (line, closed)
+}
+
+/// Splits `text 【kana】` into its parts.
+fn split_kana(s: &str) -> (String, Option<String>) {
+    let (text, kana) = reading::split(s);
+    (text.to_string(), kana.map(str::to_string))
+}
+
+/// Parses `[pos] type polarity formality`.
+fn parse_conjugation(header: &str) -> Option<ConjugationInfo> {
+    let (pos, rest) = header.strip_prefix('[')?.split_once("] ")?;
+    let (rest, formal) = rest.rsplit_once(' ')?;
+    let formal = match formal {
+        "Formal" => true,
+        "Plain" => false,
+        _ => return None,
+    };
+    let (conj_type, negative) = rest.rsplit_once(' ')?;
+    let negative = match negative {
+        "Negative" => true,
+        "Affirmative" => false,
+        _ => return None,
+    };
+    Some(ConjugationInfo {
+        pos: pos.to_string(),
+        conj_type: (conj_type != "NIL").then(|| conj_type.to_string()),
+        negative,
+        formal,
+        via: None,
+        base: None,
+    })
+}
+
+/// Parses `text 【kana】 : gloss`.
+fn parse_base_form(line: &str) -> BaseForm {
+    let (written, gloss) = line.split_once(" : ").unwrap_or((line, ""));
+    let (text, kana) = split_kana(written);
+    BaseForm {
+        text,
+        kana,
+        gloss: gloss.to_string(),
+    }
+}
+
+/// Parses `1. [pos] 《info》 gloss`, where the part of speech and info are optional.
+fn parse_sense(line: &str) -> Option<Sense> {
+    let (number, rest) = line.split_once(". ")?;
+    let number = number.parse().ok()?;
+    let (pos, rest) = match rest.strip_prefix('[').and_then(|r| r.split_once("] ")) {
+        Some((pos, rest)) => (pos.split(',').map(str::to_string).collect(), rest),
+        None => (vec![], rest),
+    };
+    let (info, gloss) = match rest.strip_prefix('《').and_then(|r| r.split_once("》 ")) {
+        Some((info, gloss)) => (Some(info.to_string()), gloss),
+        None => (None, rest),
+    };
+    Some(Sense {
+        number,
+        pos,
+        info,
+        gloss: gloss.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::fixtures;
+
+    #[test]
+    fn parses_senses() {
+        let out = fixtures().romanize_with_info("一覧は最高だぞ").unwrap();
+        let ichiran = out.entries[0].parse().unwrap();
+        assert_eq!(ichiran.romanized, "ichiran");
+        assert_eq!(ichiran.text, "一覧");
+        assert_eq!(ichiran.kana.as_deref(), Some("いちらん"));
+        assert_eq!(ichiran.senses.len(), 2);
+        assert_eq!(ichiran.senses[0].number, 1);
+        assert_eq!(ichiran.senses[0].pos, ["n", "vs", "vt"]);
+        assert_eq!(ichiran.senses[0].info, None);
+        assert!(ichiran.senses[0].gloss.starts_with("look; glance;"));
+
+        let wa = out.entries[1].parse().unwrap();
+        assert_eq!(wa.text, "は");
+        assert_eq!(wa.kana, None);
+        assert_eq!(
+            wa.senses[0].info.as_deref(),
+            Some("pronounced わ in modern Japanese")
+        );
+        assert_eq!(wa.senses[0].gloss, "indicates sentence topic");
+        assert!(wa.conjugations.is_empty());
+        assert!(wa.other.is_empty());
+    }
+
+    #[test]
+    fn parses_conjugations() {
+        let out = fixtures()
+            .romanize_with_info("「東京」に行きました。")
+            .unwrap();
+        let ikimashita = out.entries[2].parse().unwrap();
+        assert_eq!(ikimashita.text, "行きました");
+        assert!(ikimashita.senses.is_empty());
+        assert_eq!(
+            ikimashita.conjugations,
+            [ConjugationInfo {
+                pos: "v5k-s".to_string(),
+                conj_type: Some("Past (~ta)".to_string()),
+                negative: false,
+                formal: true,
+                via: None,
+                base: Some(BaseForm {
+                    text: "行く".to_string(),
+                    kana: Some("いく".to_string()),
+                    gloss: "to go; to move (towards); to head (towards)".to_string(),
+                }),
+            }]
+        );
+    }
+
+    #[test]
+    fn parses_nested_conjugations() {
+        let out = fixtures()
+            .romanize_with_info("食べさせられなかった")
+            .unwrap();
+        let details = out.entries[0].parse().unwrap();
+        assert_eq!(details.conjugations.len(), 1);
+        let past = &details.conjugations[0];
+        assert_eq!(past.conj_type.as_deref(), Some("Past (~ta)"));
+        assert_eq!(past.conjugation_type(), Some(ConjugationType::Past));
+        assert!(past.negative);
+        assert!(!past.formal);
+        assert_eq!(past.base, None);
+        let causative = past.via.as_ref().unwrap();
+        assert_eq!(causative.conj_type.as_deref(), Some("Causative-Passive"));
+        assert!(!causative.negative);
+        assert_eq!(causative.base.as_ref().unwrap().text, "食べる");
+    }
+
+    #[test]
+    fn parses_multiple_conjugations() {
+        let entry = RomanizedWithInfoEntry {
+            word: "* deshita  でした".to_string(),
+            alternatives: vec![
+                "[ Conjugation: [v1] Past (~ta) Affirmative Plain".to_string(),
+                "  食べる 【たべる】 : to eat ]".to_string(),
+                "[ Conjugation: [cop] NIL Affirmative Formal".to_string(),
+                "  だ : be; is ]".to_string(),
+            ],
+        };
+        let details = entry.parse().unwrap();
+        assert_eq!(details.conjugations.len(), 2);
+        let cop = &details.conjugations[1];
+        assert_eq!(cop.pos, "cop");
+        assert_eq!(cop.conj_type, None);
+        assert!(cop.formal);
+        assert_eq!(cop.base.as_ref().unwrap().kana, None);
+        assert_eq!(cop.base.as_ref().unwrap().gloss, "be; is");
+    }
+
+    #[test]
+    fn parses_sentence_final_particles() {
+        let out = fixtures().romanize_with_info("いい天気ですね。").unwrap();
+        let ne = out.entries[2].parse().unwrap();
+        assert_eq!(ne.text, "ね");
+        assert_eq!(ne.senses.len(), 6);
+        assert_eq!(
+            ne.senses[2].info.as_deref(),
+            Some("at sentence end; used to express one's thoughts or feelings")
+        );
+    }
+
+    #[test]
+    fn errors_on_unexpected_word_line() {
+        let entry = RomanizedWithInfoEntry {
+            word: "ichiran".to_string(),
+            alternatives: vec![],
+        };
+        assert!(matches!(
+            entry.parse(),
+            Err(IchiranError::UnexpectedOutput(_))
+        ));
+    }
+}
@@ -0,0 +1,265 @@
+//! Contains functionality for finding the dictionary form of words.
+
+use crate::{
+    lisp, reading, Alternative, Backend, CompoundWordInfo, Conj, IchiranCli, IchiranError,
+    PartOfSpeech, Via, WordInfo,
+};
+
+/// The dictionary form of a word.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Lemma {
+    pub text: String,
+    pub kana: String,
+    /// The JMdict sequence number of the dictionary form.
+    /// `None` for conjugated words, as ichiran only reports the sequence number of the conjugated form.
+    /// It can be looked up with [`IchiranCli::resolve_lemma`].
+    pub seq: Option<i32>,
+}
+
+impl WordInfo {
+    /// Returns the dictionary form of the word,
+    /// following the [`Conj::via`] chain of conjugated words to the innermost base form.
+    /// If ichiran does not report the base form of a conjugated word, the word itself is returned without a [`Lemma::seq`].
+    pub fn lemma(&self) -> Lemma {
+        let conj = match self.conj.first() {
+            Some(conj) => conj,
+            None => {
+                return Lemma {
+                    text: self.text.clone(),
+                    kana: self.kana.clone(),
+                    seq: self.seq,
+                }
+            }
+        };
+        match conj.base_reading() {
+            Some(reading) => {
+                let (text, kana) = reading::split(reading);
+                Lemma {
+                    text: text.to_string(),
+                    kana: kana.unwrap_or(text).to_string(),
+                    seq: None,
+                }
+            }
+            // the seq of the word belongs to the conjugated form
+            None => Lemma {
+                text: self.text.clone(),
+                kana: self.kana.clone(),
+                seq: None,
+            },
+        }
+    }
+}
+
+impl CompoundWordInfo {
+    /// Returns the component that carries the meaning of the compound,
+    /// which is the first component that is neither a prefix nor a suffix.
+    /// For example, the head of 食べたい is 食べ and the head of お茶 is 茶.
+    /// Falls back to the first component if every component is a prefix or a suffix.
+    pub fn head(&self) -> Option<&WordInfo> {
+        self.components
+            .iter()
+            .find(|component| component.suffix.is_none() && !is_prefix(component))
+            .or_else(|| self.components.first())
+    }
+
+    /// Returns the dictionary form of the [`CompoundWordInfo::head`] of the compound.
+    /// For example, the lemma of 食べたい is 食べる.
+    pub fn lemma(&self) -> Option<Lemma> {
+        self.head().map(WordInfo::lemma)
+    }
+}
+
+/// Returns true if every sense of the word is a prefix.
+fn is_prefix(word: &WordInfo) -> bool {
+    !word.gloss.is_empty()
+        && word.gloss.iter().all(|gloss| {
+            gloss
+                .parts_of_speech()
+                .iter()
+                .any(|pos| matches!(pos, PartOfSpeech::Pref | PartOfSpeech::NPref))
+        })
+}
+
+impl Alternative {
+    /// Returns the dictionary form of the word. See [`WordInfo::lemma`] and [`CompoundWordInfo::lemma`].
+    pub fn lemma(&self) -> Option<Lemma> {
+        match self {
+            Self::WordInfo(info) => Some(info.lemma()),
+            Self::CompoundWordInfo(info) => info.lemma(),
+        }
+    }
+}
+
+impl<B: Backend> IchiranCli<B> {
+    /// Returns the dictionary form of the word like [`Alternative::lemma`],
+    /// looking up the sequence number of the dictionary form of conjugated words from ichiran's database.
+    /// [`Lemma::seq`] is `None` if no matching entry is found.
+    pub fn resolve_lemma(&self, alternative: &Alternative) -> Result<Option<Lemma>, IchiranError> {
+        let word = match alternative {
+            Alternative::WordInfo(info) => info,
+            Alternative::CompoundWordInfo(info) => match info.head() {
+                Some(head) => head,
+                None => return Ok(None),
+            },
+        };
+        let mut lemma = word.lemma();
+        if let (None, Some(seq)) = (lemma.seq, word.seq) {
+            let base_seq = self.eval(&base_seq_expr(seq, &lemma.text))?;
+            let base_seq = base_seq.trim();
+            if !base_seq.is_empty() {
+                lemma.seq = Some(
+                    base_seq
+                        .parse()
+                        .map_err(|_| IchiranError::UnexpectedOutput(base_seq.to_string()))?,
+                );
+            }
+        }
+        Ok(Some(lemma))
+    }
+}
+
+/// Returns a Lisp expression that prints the sequence number of the entry with the given text
+/// that the conjugated form with the given sequence number was derived from, or nothing.
+fn base_seq_expr(seq: i32, text: &str) -> String {
+    let query = r#"SELECT c."from" FROM conjugation c WHERE c.seq = $1 AND (EXISTS (SELECT 1 FROM kanji_text k WHERE k.seq = c."from" AND k.text = $2) OR EXISTS (SELECT 1 FROM kana_text k WHERE k.seq = c."from" AND k.text = $2)) ORDER BY c.id LIMIT 1"#;
+    format!(
+        "(or (postmodern:query {} {seq} {} :single) \"\")",
+        lisp::string(query),
+        lisp::string(text)
+    )
+}
+
+impl Conj {
+    /// Returns the reading of the unconjugated word, such as `食べる 【たべる】`.
+    pub fn base_reading(&self) -> Option<&str> {
+        match self.via.first() {
+            Some(via) => via.base_reading(),
+            None => self.reading.as_deref(),
+        }
+    }
+}
+
+impl Via {
+    /// Returns the reading of the unconjugated word, such as `食べる 【たべる】`.
+    pub fn base_reading(&self) -> Option<&str> {
+        match self.via.first() {
+            Some(via) => via.base_reading(),
+            None => self.reading.as_deref(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing;
+
+    fn lemma(input: &str) -> Option<Lemma> {
+        let segmented = testing::fixtures().segment(input, None).unwrap();
+        testing::alternatives(&segmented, 0, 0)[0].lemma()
+    }
+
+    #[test]
+    fn finds_lemma_of_plain_words() {
+        assert_eq!(
+            lemma("一覧は最高だぞ").unwrap(),
+            Lemma {
+                text: "一覧".to_string(),
+                kana: "いちらん".to_string(),
+                seq: Some(1160790),
+            }
+        );
+    }
+
+    #[test]
+    fn finds_lemma_of_conjugated_words() {
+        let taberu = Lemma {
+            text: "食べる".to_string(),
+            kana: "たべる".to_string(),
+            seq: None,
+        };
+        assert_eq!(lemma("食べさせられなかった").unwrap(), taberu);
+        assert_eq!(lemma("食べたい").unwrap(), taberu);
+    }
+
+    #[test]
+    fn leaves_seq_of_conjugated_words_without_base_empty() {
+        // hand-written, a conjugation without the reading of the base form
+        let segmented = testing::segment_with(
+            "食べた",
+            r#"[[[[["tabeta",{"reading":"食べた 【たべた】","text":"食べた","kana":"たべた","score":100,"seq":10441330,"conj":[{"prop":[{"pos":"v1","type":"Past (~ta)"}],"readok":true}]},[]]],100]]]"#,
+        );
+        assert_eq!(
+            testing::alternatives(&segmented, 0, 0)[0].lemma().unwrap(),
+            Lemma {
+                text: "食べた".to_string(),
+                kana: "たべた".to_string(),
+                seq: None,
+            }
+        );
+    }
+
+    #[test]
+    fn finds_lemma_of_prefixed_compounds() {
+        // hand-written, お茶 split into the honorific prefix and 茶
+        let segmented = testing::segment_with(
+            "お茶",
+            r#"[[[[["ocha",{"reading":"お茶 【おちゃ】","text":"お茶","kana":"おちゃ","score":120,"compound":["お","茶"],"components":[{"reading":"お","text":"お","kana":"お","score":0,"seq":2826528,"gloss":[{"pos":"[pref]","gloss":"honorific/polite/humble prefix"}]},{"reading":"茶 【ちゃ】","text":"茶","kana":"ちゃ","score":0,"seq":1443930,"gloss":[{"pos":"[n]","gloss":"tea (usu. green)"}]}]},[]]],120]]]"#,
+        );
+        let compound = testing::compound_word_info(&segmented, 0, 0);
+        assert_eq!(compound.head().unwrap().text, "茶");
+        assert_eq!(
+            compound.lemma().unwrap(),
+            Lemma {
+                text: "茶".to_string(),
+                kana: "ちゃ".to_string(),
+                seq: Some(1443930),
+            }
+        );
+    }
+
+    #[test]
+    fn resolves_seq_of_conjugated_words() {
+        let mut backend = testing::fixture_backend();
+        backend.insert(&["-e"], &base_seq_expr(10441370, "食べる"), "1358280\n");
+        let cli = IchiranCli::with_backend(backend);
+        let segmented = cli.segment("食べさせられなかった", None).unwrap();
+        let alternative = &testing::alternatives(&segmented, 0, 0)[0];
+        assert_eq!(
+            cli.resolve_lemma(alternative).unwrap().unwrap(),
+            Lemma {
+                text: "食べる".to_string(),
+                kana: "たべる".to_string(),
+                seq: Some(1358280),
+            }
+        );
+
+        // plain words already have a sequence number and need no lookup
+        let segmented = cli.segment("一覧は最高だぞ", None).unwrap();
+        let alternative = &testing::alternatives(&segmented, 0, 0)[0];
+        assert_eq!(
+            cli.resolve_lemma(alternative).unwrap().unwrap().seq,
+            Some(1160790)
+        );
+    }
+
+    #[test]
+    fn leaves_seq_of_unknown_base_empty() {
+        let mut backend = testing::fixture_backend();
+        backend.insert(&["-e"], &base_seq_expr(10441370, "食べる"), "\n");
+        let cli = IchiranCli::with_backend(backend);
+        let segmented = cli.segment("食べさせられなかった", None).unwrap();
+        let alternative = &testing::alternatives(&segmented, 0, 0)[0];
+        assert_eq!(cli.resolve_lemma(alternative).unwrap().unwrap().seq, None);
+    }
+
+    #[test]
+    fn follows_nested_derivations() {
+        // 食べさせられた as causative → passive → past
+        let segmented = testing::segment_with("食べさせられた", testing::NESTED_VIA);
+        let lemma = testing::alternatives(&segmented, 0, 0)[0].lemma().unwrap();
+        assert_eq!(lemma.text, "食べる");
+        assert_eq!(lemma.kana, "たべる");
+    }
+}
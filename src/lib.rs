@@ -1,10 +1,56 @@
 //! Bindings for ichiran-cli.
 
+#[cfg(feature = "tokio")]
+mod asynchronous;
+mod backend;
+mod batch;
+mod cache;
+mod chunk;
+mod conj;
+mod deinflect;
+mod entry;
+mod fixture;
+pub mod furigana;
+pub mod html;
+mod info;
+mod lemma;
+pub mod lisp;
+pub mod offsets;
+mod pos;
+mod process;
 pub mod raw;
+mod reading;
+mod record;
+mod request;
 mod rusty;
+mod session;
+mod stream;
+#[cfg(test)]
+mod testing;
+mod version;
 
-pub use self::rusty::*;
-use std::{path::PathBuf, process::Command};
+#[cfg(feature = "tokio")]
+pub use self::asynchronous::{AsyncBackend, AsyncIchiranCli, BackendFuture, BlockingBackend};
+pub use self::{
+    backend::{Backend, ProcessBackend},
+    cache::CachingBackend,
+    conj::ConjugationType,
+    deinflect::{Deinflection, DeinflectionStep},
+    entry::{DictionaryEntry, EntrySense, KanaElement, KanjiElement},
+    fixture::FixtureBackend,
+    info::{BaseForm, ConjugationInfo, Sense, WordDetails},
+    lemma::Lemma,
+    lisp::RomanizationMethod,
+    pos::PartOfSpeech,
+    reading::Reading,
+    record::{Exchange, RecordingBackend, ReplayBackend},
+    rusty::*,
+    session::IchiranSession,
+    stream::SegmentLines,
+    version::IchiranVersion,
+};
+use request::{Mode, Request};
+use std::{ffi::OsStr, io::BufRead, path::PathBuf, process::Output, time::Duration};
 use thiserror::Error;
 
 /// Crate error type.
@@ -20,98 +66,417 @@ pub enum IchiranError {
     IchiranError { stdout: String, stderr: String },
     #[error("Error while deserializing ichiran-cli output")]
     Deserialization(#[from] serde_path_to_error::Error<serde_json::Error>),
+    #[error("ichiran-cli session exited unexpectedly")]
+    SessionExited { stderr: String },
+    #[error("ichiran-cli did not finish within the timeout and was killed")]
+    Timeout { elapsed: Duration, stderr: String },
+    #[error("No recorded output for the ichiran-cli call")]
+    NotRecorded { args: Vec<String>, input: String },
+    #[error("Error while writing a recording")]
+    RecordingError(#[source] std::io::Error),
+    #[error("Error while accessing the cache")]
+    CacheError(#[source] std::io::Error),
+    #[error("Error while reading input")]
+    ReadError(#[source] std::io::Error),
+    #[error("The output format of this version of ichiran is not supported")]
+    Incompatible { version: String, reason: String },
 }
 
+/// A max length of the input passed to a single `ichiran-cli` call in bytes for use with [`IchiranCliBuilder::max_chunk_len`].
+/// Well below the limits on the length of a single command-line argument on common platforms.
+pub const DEFAULT_MAX_CHUNK_LEN: usize = 8 * 1024;
+
 /// Wrapper for ichiran-cli.
-#[derive(Debug)]
-pub struct IchiranCli {
-    cli_path: PathBuf,
+///
+/// Calls `ichiran-cli` through a [`Backend`], which by default is a [`ProcessBackend`] that spawns a new process for every call.
+#[derive(Debug, Clone)]
+pub struct IchiranCli<B = ProcessBackend> {
+    backend: B,
+    default_limit: Option<u32>,
+    max_chunk_len: Option<usize>,
 }
 
 impl IchiranCli {
     /// Takes a path to the `ichiran-cli` binary.
     pub fn new(cli_path: PathBuf) -> Self {
-        Self { cli_path }
+        IchiranCliBuilder::new(cli_path).build()
+    }
+
+    /// Returns a builder for configuring how `ichiran-cli` is invoked.
+    /// The program can be the `ichiran-cli` binary itself or a wrapper such as `docker` or `nice`.
+    pub fn builder(program: impl Into<PathBuf>) -> IchiranCliBuilder {
+        IchiranCliBuilder::new(program)
+    }
+
+    /// Sets the max amount of time each call to `ichiran-cli` is allowed to take before the process is killed.
+    /// `None` disables the timeout, which is the default.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.backend.timeout = timeout;
+    }
+
+    /// Returns a copy of this wrapper with the given timeout, for setting a timeout for a single call.
+    /// ```no_run
+    /// # use ichiran::IchiranCli;
+    /// # use std::{path::PathBuf, time::Duration};
+    /// let cli = IchiranCli::new(PathBuf::from("./data/ichiran-cli"));
+    /// let romanized = cli.with_timeout(Duration::from_secs(5)).romanize("一覧")?;
+    /// # Ok::<(), ichiran::IchiranError>(())
+    /// ```
+    pub fn with_timeout(&self, timeout: Duration) -> Self {
+        let mut cli = self.clone();
+        cli.set_timeout(Some(timeout));
+        cli
+    }
+
+    /// Starts a persistent [`IchiranSession`] using the same `ichiran-cli` binary, default limit and max chunk length.
+    /// The timeout applies to each call made through the session.
+    pub fn session(&self) -> Result<IchiranSession, IchiranError> {
+        IchiranSession::start(
+            self.backend.command(),
+            self.default_limit,
+            self.max_chunk_len,
+            self.backend.timeout,
+        )
+    }
+}
+
+impl<B> IchiranCli<B> {
+    /// Uses the given backend to call `ichiran-cli`.
+    pub fn with_backend(backend: B) -> Self {
+        Self {
+            backend,
+            default_limit: None,
+            max_chunk_len: None,
+        }
     }
 
+    /// Returns the backend used to call `ichiran-cli`.
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    /// Sets the limit used by [`IchiranCli::segment`] when it is called without one.
+    pub fn set_default_limit(&mut self, limit: Option<u32>) {
+        self.default_limit = limit;
+    }
+
+    /// Sets the max length of the input passed to a single `ichiran-cli` call in bytes.
+    /// `None` disables chunking. See [`IchiranCliBuilder::max_chunk_len`].
+    pub fn set_max_chunk_len(&mut self, max_chunk_len: Option<usize>) {
+        self.max_chunk_len = max_chunk_len;
+    }
+
+    /// Returns a request for the input, split into chunks if it is longer than the max chunk length.
+    fn request<'a>(&self, mode: Mode, input: &'a str) -> Request<'a> {
+        Request::new(mode, input, self.max_chunk_len)
+    }
+
+    /// Returns the limit that should be passed to `ichiran-cli -l`, if any.
+    fn limit(&self, limit: Option<u32>) -> Option<u32> {
+        limit.or(self.default_limit)
+    }
+}
+
+impl<B: Backend> IchiranCli<B> {
     /// Calls and parses the output of `ichiran-cli -f`.
     /// The optional limit argument defines the max number of alternative segmentations that are returned for each segment.
+    /// If it is `None`, the default limit set with [`IchiranCliBuilder::default_limit`] is used.
+    ///
+    /// Inputs longer than the max chunk length are split into chunks and the results are stitched back together.
     pub fn segment(&self, input: &str, limit: Option<u32>) -> Result<Vec<Segment>, IchiranError> {
-        let (stdout, _stderr) = if let Some(limit) = limit {
-            self.run(&["-f", "-l", &limit.to_string(), input])?
-        } else {
-            self.run(&["-f", input])?
-        };
-        let jd = &mut serde_json::Deserializer::from_str(&stdout);
-        let info: raw::FullSplitInfo = serde_path_to_error::deserialize(jd)?;
-        Ok(info.into())
+        let request = self.request(Mode::Full(self.limit(limit)), input);
+        request.segment(self.run_request(&request)?)
+    }
+
+    /// Calls `ichiran-cli -f` and returns the output as [`raw::FullSplitInfo`], which keeps all of the data output by ichiran.
+    /// See [`raw::FullSplitInfo::unknown_fields`] for detecting changes in ichiran's output.
+    ///
+    /// Unlike [`IchiranCli::segment`], the input is never split into chunks.
+    pub fn segment_raw(
+        &self,
+        input: &str,
+        limit: Option<u32>,
+    ) -> Result<raw::FullSplitInfo, IchiranError> {
+        let args = Mode::Full(self.limit(limit)).args();
+        let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+        let (stdout, _stderr) = self.backend.run(&args, input)?;
+        parse_full_split_info(&stdout)
+    }
+
+    /// Calls [`IchiranCli::segment`] and attaches the spans that each segment and word cover in the input with [`offsets::align`].
+    pub fn segment_with_spans(
+        &self,
+        input: &str,
+        limit: Option<u32>,
+    ) -> Result<Vec<offsets::SpannedSegment>, IchiranError> {
+        let segmented = self.segment(input, limit)?;
+        Ok(offsets::align(input, segmented))
     }
 
     /// Calls and parses the output of `ichiran-cli -i`.
+    ///
+    /// Inputs longer than the max chunk length are split into chunks like in [`IchiranCli::romanize`],
+    /// and the entries of the chunks are concatenated.
     pub fn romanize_with_info(&self, input: &str) -> Result<RomanizedWithInfo, IchiranError> {
-        let (stdout, _stderr) = self.run(&["-i", input])?;
-        let mut lines = stdout.lines();
-        let mut romanized = lines
-            .next()
-            .ok_or_else(|| IchiranError::UnexpectedOutput(stdout.clone()))?
-            .to_string();
-        let trimmed = romanized.trim_end().len();
-        romanized.truncate(trimmed);
-        let mut entries = vec![];
-        let mut word = None;
-        let mut alternatives = vec![];
-        while let Some(line) = lines.next() {
-            if line.is_empty() {
-                if let Some(w) = word {
-                    entries.push(RomanizedWithInfoEntry {
-                        word: w,
-                        alternatives,
-                    });
-                    word = None;
-                    alternatives = vec![];
-                }
-            } else {
-                if word.is_some() {
-                    alternatives.push(line.to_string());
-                } else {
-                    word = Some(line.to_string());
-                }
-            }
-        }
-        Ok(RomanizedWithInfo { romanized, entries })
+        let request = self.request(Mode::Info, input);
+        request.romanize_with_info(self.run_request(&request)?)
     }
 
     /// Calls and parses the output of `ichiran-cli` without any flags.
+    ///
+    /// Inputs longer than the max chunk length are split into chunks, and the results are joined with the whitespace
+    /// between the chunks, or with a space if there is none.
     pub fn romanize(&self, input: &str) -> Result<String, IchiranError> {
-        let (mut stdout, _stderr) = self.run(&[input])?;
-        // truncate to cut off the newline
-        let trimmed = stdout.trim_end().len();
-        stdout.truncate(trimmed);
-        Ok(stdout)
-    }
-
-    fn run(&self, args: &[&str]) -> Result<(String, String), IchiranError> {
-        let out = Command::new(&self.cli_path)
-            .args(args)
-            .output()
-            .map_err(IchiranError::CommandError)?;
-        let stdout = String::from_utf8(out.stdout)?;
-        let stderr = String::from_utf8(out.stderr)?;
-        if out.status.success() {
-            Ok((stdout, stderr))
+        let request = self.request(Mode::Plain, input);
+        request.romanize(self.run_request(&request)?)
+    }
+
+    /// Calls `ichiran-cli` for each input of the request and returns the stdout of the calls.
+    fn run_request(&self, request: &Request) -> Result<Vec<String>, IchiranError> {
+        let args = request.mode.args();
+        let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+        request
+            .inputs()
+            .map(|input| Ok(self.backend.run(&args, input)?.0))
+            .collect()
+    }
+
+    /// Segments each input with [`IchiranCli::segment`], running at most `workers` calls at the same time.
+    /// The results are returned in the same order as the inputs, and a failure for one input does not affect the others.
+    pub fn segment_batch<I, S>(
+        &self,
+        inputs: I,
+        limit: Option<u32>,
+        workers: usize,
+    ) -> Vec<Result<Vec<Segment>, IchiranError>>
+    where
+        B: Sync,
+        I: IntoIterator<Item = S>,
+        S: AsRef<str> + Sync,
+    {
+        let inputs = inputs.into_iter().collect();
+        batch::run(inputs, workers, |input: &S| {
+            self.segment(input.as_ref(), limit)
+        })
+    }
+
+    /// Romanizes each input with [`IchiranCli::romanize`], running at most `workers` calls at the same time.
+    /// The results are returned in the same order as the inputs, and a failure for one input does not affect the others.
+    pub fn romanize_batch<I, S>(
+        &self,
+        inputs: I,
+        workers: usize,
+    ) -> Vec<Result<String, IchiranError>>
+    where
+        B: Sync,
+        I: IntoIterator<Item = S>,
+        S: AsRef<str> + Sync,
+    {
+        let inputs = inputs.into_iter().collect();
+        batch::run(inputs, workers, |input: &S| self.romanize(input.as_ref()))
+    }
+
+    /// Returns an iterator that lazily segments each line of the reader, along with the zero-based line index.
+    /// ```no_run
+    /// # use ichiran::IchiranCli;
+    /// # use std::{fs::File, io::BufReader, path::PathBuf};
+    /// let cli = IchiranCli::new(PathBuf::from("./data/ichiran-cli"));
+    /// let book = BufReader::new(File::open("./data/book")?);
+    /// for (line_index, segmentation) in cli.segment_lines(book).resume_from(100) {
+    ///     println!("line {}: {segmentation:?}", line_index + 1);
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn segment_lines<R: BufRead>(&self, reader: R) -> SegmentLines<'_, B, R> {
+        SegmentLines::new(self, reader)
+    }
+}
+
+/// Builder for [`IchiranCli`].
+/// ```no_run
+/// # use ichiran::IchiranCli;
+/// # use std::time::Duration;
+/// let cli = IchiranCli::builder("docker")
+///     .args(["exec", "-i", "ichiran-main", "ichiran-cli"])
+///     .env("LANG", "C.UTF-8")
+///     .default_limit(3)
+///     .timeout(Duration::from_secs(30))
+///     .build();
+/// let segmentation = cli.segment("一覧は最高だぞ", None)?;
+/// # Ok::<(), ichiran::IchiranError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct IchiranCliBuilder {
+    cli: IchiranCli,
+}
+
+impl IchiranCliBuilder {
+    /// Takes the program to run, usually a path to the `ichiran-cli` binary.
+    pub fn new(program: impl Into<PathBuf>) -> Self {
+        Self {
+            cli: IchiranCli::with_backend(ProcessBackend::new(program)),
+        }
+    }
+
+    /// Adds an argument that is passed to the program before the arguments for `ichiran-cli`.
+    pub fn arg(mut self, arg: impl AsRef<OsStr>) -> Self {
+        self.cli
+            .backend
+            .prefix_args
+            .push(arg.as_ref().to_os_string());
+        self
+    }
+
+    /// Adds arguments that are passed to the program before the arguments for `ichiran-cli`.
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        self.cli
+            .backend
+            .prefix_args
+            .extend(args.into_iter().map(|a| a.as_ref().to_os_string()));
+        self
+    }
+
+    /// Sets an environment variable for the program.
+    pub fn env(mut self, key: impl AsRef<OsStr>, val: impl AsRef<OsStr>) -> Self {
+        self.cli
+            .backend
+            .envs
+            .push((key.as_ref().to_os_string(), val.as_ref().to_os_string()));
+        self
+    }
+
+    /// Sets environment variables for the program.
+    pub fn envs<I, K, V>(mut self, vars: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        self.cli.backend.envs.extend(
+            vars.into_iter()
+                .map(|(k, v)| (k.as_ref().to_os_string(), v.as_ref().to_os_string())),
+        );
+        self
+    }
+
+    /// Sets the working directory of the program.
+    pub fn current_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.cli.backend.current_dir = Some(dir.into());
+        self
+    }
+
+    /// Sets the limit used by [`IchiranCli::segment`] when it is called without one.
+    pub fn default_limit(mut self, limit: u32) -> Self {
+        self.cli.default_limit = Some(limit);
+        self
+    }
+
+    /// Sets the max length of the input passed to a single `ichiran-cli` call in bytes, such as [`DEFAULT_MAX_CHUNK_LEN`].
+    /// Longer inputs given to [`IchiranCli::segment`], [`IchiranCli::romanize`] and [`IchiranCli::romanize_with_info`] are split into chunks,
+    /// preferably at sentence boundaries such as 。, ！, ？, newlines and closing brackets,
+    /// and the results are stitched back together.
+    /// This avoids hitting the OS limit on the length of command-line arguments.
+    ///
+    /// Chunking is disabled by default.
+    /// Ichiran segments each chunk on its own, so words are never segmented across a chunk boundary,
+    /// and the results can differ from processing the whole input in one call if a chunk has to be split outside of a sentence boundary.
+    pub fn max_chunk_len(mut self, max_chunk_len: usize) -> Self {
+        self.cli.max_chunk_len = Some(max_chunk_len);
+        self
+    }
+
+    /// Sets the max amount of time each call to `ichiran-cli` is allowed to take before the process is killed.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.cli.backend.timeout = Some(timeout);
+        self
+    }
+
+    /// Builds the [`IchiranCli`].
+    pub fn build(self) -> IchiranCli {
+        self.cli
+    }
+}
+
+/// Checks the exit status of `ichiran-cli` and returns its stdout and stderr.
+fn parse_output(out: Output) -> Result<(String, String), IchiranError> {
+    let stdout = String::from_utf8(out.stdout)?;
+    let stderr = String::from_utf8(out.stderr)?;
+    if out.status.success() {
+        Ok((stdout, stderr))
+    } else {
+        Err(IchiranError::IchiranError { stdout, stderr })
+    }
+}
+
+/// Parses the output of `ichiran-cli -f`.
+fn parse_segment(stdout: &str) -> Result<Vec<Segment>, IchiranError> {
+    Ok(parse_full_split_info(stdout)?.into())
+}
+
+/// Parses the output of `ichiran-cli -f` into the raw types.
+fn parse_full_split_info(stdout: &str) -> Result<raw::FullSplitInfo, IchiranError> {
+    let jd = &mut serde_json::Deserializer::from_str(stdout);
+    Ok(serde_path_to_error::deserialize(jd)?)
+}
+
+/// Parses the output of `ichiran-cli -i`.
+fn parse_romanize_with_info(stdout: String) -> Result<RomanizedWithInfo, IchiranError> {
+    let mut lines = stdout.lines();
+    let mut romanized = lines
+        .next()
+        .ok_or_else(|| IchiranError::UnexpectedOutput(stdout.clone()))?
+        .to_string();
+    let trimmed = romanized.trim_end().len();
+    romanized.truncate(trimmed);
+    let mut entries = vec![];
+    let mut word = None;
+    let mut alternatives = vec![];
+    for line in lines {
+        if line.is_empty() {
+            if let Some(w) = word {
+                entries.push(RomanizedWithInfoEntry {
+                    word: w,
+                    alternatives,
+                });
+                word = None;
+                alternatives = vec![];
+            }
+        } else if word.is_some() {
+            alternatives.push(line.to_string());
         } else {
-            Err(IchiranError::IchiranError { stdout, stderr })
+            word = Some(line.to_string());
         }
     }
+    // the output does not end with a blank line, so the last word is pushed here
+    if let Some(word) = word {
+        entries.push(RomanizedWithInfoEntry { word, alternatives });
+    }
+    Ok(RomanizedWithInfo { romanized, entries })
+}
+
+/// Parses the output of `ichiran-cli` without any flags.
+fn parse_romanize(mut stdout: String) -> String {
+    // truncate to cut off the newline
+    let trimmed = stdout.trim_end().len();
+    stdout.truncate(trimmed);
+    stdout
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RomanizedWithInfo {
     pub romanized: String,
     pub entries: Vec<RomanizedWithInfoEntry>,
 }
 
+/// A single word from the output of `ichiran-cli -i`, as the raw lines printed by ichiran.
+/// See [`RomanizedWithInfoEntry::parse`] for a structured version.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RomanizedWithInfoEntry {
     pub word: String,
     pub alternatives: Vec<String>,
@@ -120,6 +485,8 @@ pub struct RomanizedWithInfoEntry {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::testing::{self, fixtures};
+    use std::collections::BTreeSet;
 
     fn ichiran() -> IchiranCli {
         IchiranCli::new(PathBuf::from("./data/ichiran-cli"))
@@ -137,9 +504,24 @@ mod test {
         assert_eq!(out, "ichiran wa saikō da zo");
     }
 
+    #[test]
+    fn romanizes_from_fixtures() {
+        let out = fixtures().romanize("一覧は最高だぞ").unwrap();
+        assert_eq!(out, "ichiran wa saikō da zo");
+    }
+
     #[test]
     fn romanizes_with_info() {
-        let out = ichiran().romanize_with_info("一覧は最高だぞ").unwrap();
+        check_romanizes_with_info(&ichiran());
+    }
+
+    #[test]
+    fn romanizes_with_info_from_fixtures() {
+        check_romanizes_with_info(&fixtures());
+    }
+
+    fn check_romanizes_with_info(ichiran: &IchiranCli<impl Backend>) {
+        let out = ichiran.romanize_with_info("一覧は最高だぞ").unwrap();
         assert_eq!(out.romanized, "ichiran wa saikō da zo");
         assert_eq!(
             out.entries[0],
@@ -181,7 +563,36 @@ mod test {
                 alternatives: vec!["1. [aux-v,cop-da,cop] 《plain copula》 be; is".to_string()]
             }
         );
-        assert_eq!(out.entries.len(), 4);
+        assert_eq!(
+            out.entries[4],
+            RomanizedWithInfoEntry {
+                word: "* zo  ぞ".to_string(),
+                alternatives: vec![
+                    "1. [prt] 《at sentence end》 adds force or indicates command".to_string()
+                ]
+            }
+        );
+        assert_eq!(out.entries.len(), 5);
+    }
+
+    #[test]
+    fn romanizes_every_word_with_info() {
+        let cases: [(&str, &[&str]); 4] = [
+            ("一覧は最高だぞ", &["ichiran", "wa", "saikō", "da", "zo"]),
+            ("いい天気ですね。", &["iitenki", "desu", "ne"]),
+            ("食べさせられなかった", &["tabesaserarenakatta"]),
+            ("「東京」に行きました。", &["Tōkyō", "ni", "ikimashita"]),
+        ];
+        let ichiran = fixtures();
+        for (input, words) in cases {
+            let out = ichiran.romanize_with_info(input).unwrap();
+            let romanized = out
+                .entries
+                .iter()
+                .map(|entry| entry.parse().unwrap().romanized)
+                .collect::<Vec<_>>();
+            assert_eq!(romanized, words, "{input}");
+        }
     }
 
     #[test]
@@ -192,17 +603,367 @@ mod test {
 
     #[test]
     fn uses_limit() {
-        let ichiran = ichiran();
+        check_uses_limit(&ichiran());
+    }
+
+    #[test]
+    fn uses_limit_from_fixtures() {
+        check_uses_limit(&fixtures());
+    }
+
+    fn check_uses_limit(ichiran: &IchiranCli<impl Backend>) {
         let segmented = ichiran.segment("一人目", None).unwrap();
-        let Segment::Segmentations(segmentations) = &segmented[0] else {
+        assert_eq!(testing::segmentations(&segmented, 0).len(), 1);
+        let segmented = ichiran.segment("一人目", Some(2)).unwrap();
+        assert_eq!(testing::segmentations(&segmented, 0).len(), 2);
+    }
+
+    #[test]
+    fn segments_fixtures() {
+        let ichiran = fixtures();
+        for input in [
+            "一覧は最高だぞ",
+            "いい天気ですね。",
+            "一人目",
+            "食べたい",
+            "食べさせられなかった",
+            "はし",
+            "「東京」に行きました。",
+        ] {
+            ichiran.segment(input, None).unwrap();
+        }
+    }
+
+    #[test]
+    fn converts_compounds_and_suffixes() {
+        let segmented = fixtures().segment("食べたい", None).unwrap();
+        let compound = testing::compound_word_info(&segmented, 0, 0);
+        assert_eq!(compound.compound, ["食べ", "たい"]);
+        let [tabe, tai] = compound.components.as_slice() else {
+            panic!("{compound:?}");
+        };
+        assert_eq!(tabe.conj[0].reading.as_deref(), Some("食べる 【たべる】"));
+        assert_eq!(
+            tabe.conj[0].prop[0].prop_type.as_deref(),
+            Some("Continuative (~i)")
+        );
+        assert_eq!(
+            tai.suffix.as_deref(),
+            Some("want to ... / would like to ...")
+        );
+    }
+
+    #[test]
+    fn converts_counters() {
+        let segmented = fixtures().segment("一人目", Some(2)).unwrap();
+        let counters = testing::segmentations(&segmented, 0)
+            .iter()
+            .map(|s| match &s.words[0].alternatives[0] {
+                Alternative::WordInfo(info) => info.counter.clone().unwrap(),
+                other => panic!("{other:?}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            counters,
+            [
+                Counter {
+                    value: "Value: 1st".to_string(),
+                    ordinal: true
+                },
+                Counter {
+                    value: "Value: 1".to_string(),
+                    ordinal: false
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn converts_conjugations() {
+        let segmented = fixtures().segment("食べさせられなかった", None).unwrap();
+        let info = testing::word_info(&segmented, 0, 0);
+        let conj = &info.conj[0];
+        assert_eq!(conj.prop[0].prop_type.as_deref(), Some("Past (~ta)"));
+        assert!(conj.prop[0].neg);
+        assert_eq!(conj.reading, None);
+        assert_eq!(
+            conj.via[0].prop[0].prop_type.as_deref(),
+            Some("Causative-Passive")
+        );
+        assert_eq!(conj.via[0].reading.as_deref(), Some("食べる 【たべる】"));
+
+        let segmented = fixtures().segment("いい天気ですね。", None).unwrap();
+        let desu = testing::word_info(&segmented, 0, 1);
+        assert_eq!(desu.conj[0].prop[0].prop_type, None);
+        assert!(desu.conj[0].prop[0].fml);
+    }
+
+    #[test]
+    fn converts_alternatives() {
+        let segmented = fixtures().segment("はし", None).unwrap();
+        let seqs = testing::alternatives(&segmented, 0, 0)
+            .iter()
+            .map(|a| match a {
+                Alternative::WordInfo(info) => info.seq,
+                other => panic!("{other:?}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(seqs, [Some(1489750), Some(1489800)]);
+    }
+
+    #[test]
+    fn converts_punctuation() {
+        let segmented = fixtures().segment("「東京」に行きました。", None).unwrap();
+        let others = segmented
+            .iter()
+            .filter_map(|s| match s {
+                Segment::Other(other) => Some(other.as_str()),
+                Segment::Segmentations(_) => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(others, [" \"", "\" ", ". "]);
+        assert_eq!(segmented.len(), 5);
+    }
+
+    #[test]
+    fn segments_batches() {
+        let inputs = ["一人目", "未知", "はし"];
+        let segmented = fixtures().segment_batch(inputs, Some(2), 2);
+        assert_eq!(segmented.len(), 3);
+        assert_eq!(
+            segmented[0].as_ref().unwrap(),
+            &fixtures().segment("一人目", Some(2)).unwrap()
+        );
+        assert!(matches!(
+            segmented[1],
+            Err(IchiranError::NotRecorded { .. })
+        ));
+        assert!(segmented[2].is_err());
+
+        let romanized = fixtures().romanize_batch(vec!["いい天気ですね。".to_string()], 2);
+        assert_eq!(romanized[0].as_ref().unwrap(), "iitenki desu ne.");
+    }
+
+    #[test]
+    fn segments_in_chunks() {
+        let mut chunked = ichiran();
+        chunked.set_max_chunk_len(Some(30));
+        for input in ["いい天気ですね。一人目", "いい天気ですね。\n一人目"] {
+            assert_eq!(
+                chunked.segment(input, None).unwrap(),
+                ichiran().segment(input, None).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn segments_in_chunks_from_fixtures() {
+        let mut ichiran = fixtures();
+        ichiran.set_max_chunk_len(Some(30));
+        let segmented = ichiran.segment("いい天気ですね。\n一人目", None).unwrap();
+        let first = fixtures().segment("いい天気ですね。", None).unwrap();
+        let second = fixtures().segment("一人目", None).unwrap();
+        assert_eq!(segmented.len(), first.len() + second.len());
+        assert_eq!(segmented[0], first[0]);
+        // the newline between the chunks is kept in the punctuation at the end of the first chunk
+        assert!(matches!(&segmented[1], Segment::Other(text) if text.ends_with('\n')));
+        assert_eq!(segmented[2..], second[..]);
+    }
+
+    #[test]
+    fn romanizes_in_chunks() {
+        let mut ichiran = fixtures();
+        ichiran.set_max_chunk_len(Some(30));
+        let input = "いい天気ですね。\n一覧は最高だぞ";
+        assert_eq!(
+            ichiran.romanize(input).unwrap(),
+            "iitenki desu ne.\nichiran wa saikō da zo"
+        );
+
+        let out = ichiran.romanize_with_info(input).unwrap();
+        assert_eq!(out.romanized, "iitenki desu ne.\nichiran wa saikō da zo");
+        let words = out
+            .entries
+            .iter()
+            .map(|entry| entry.parse().unwrap().romanized)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            words,
+            ["iitenki", "desu", "ne", "ichiran", "wa", "saikō", "da", "zo"]
+        );
+    }
+
+    #[test]
+    fn chunks_only_when_enabled() {
+        let input = "一人目。".repeat(DEFAULT_MAX_CHUNK_LEN / "一人目。".len() + 1);
+        let err = fixtures().romanize(&input).unwrap_err();
+        let IchiranError::NotRecorded { input: called, .. } = err else {
+            panic!("{err:?}");
+        };
+        assert_eq!(called, input);
+
+        let mut ichiran = fixtures();
+        ichiran.set_max_chunk_len(Some(DEFAULT_MAX_CHUNK_LEN));
+        let err = ichiran.romanize(&input).unwrap_err();
+        let IchiranError::NotRecorded { input: called, .. } = err else {
+            panic!("{err:?}");
+        };
+        assert!(called.len() <= DEFAULT_MAX_CHUNK_LEN);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn round_trips_raw_output() {
+        for entry in std::fs::read_dir("./fixtures/full").unwrap() {
+            let json = std::fs::read_to_string(entry.unwrap().path()).unwrap();
+            let raw = serde_json::from_str::<raw::FullSplitInfo>(&json).unwrap();
+            assert_eq!(
+                serde_json::to_value(&raw).unwrap(),
+                serde_json::from_str::<serde_json::Value>(&json).unwrap()
+            );
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn round_trips_segments() {
+        let segmented = fixtures().segment("「東京」に行きました。", None).unwrap();
+        let json = serde_json::to_value(&segmented).unwrap();
+        assert_eq!(
+            json[0],
+            serde_json::json!({ "type": "other", "value": " \"" })
+        );
+        assert_eq!(json[1]["type"], "segmentations");
+        let alternative = &json[3]["value"][0]["words"][1]["alternatives"][0];
+        assert_eq!(alternative["type"], "word_info");
+        assert_eq!(alternative["value"]["conj"][0]["prop"][0]["pos"], "v5k-s");
+        assert_eq!(
+            serde_json::from_value::<Vec<Segment>>(json).unwrap(),
+            segmented
+        );
+
+        let pos = serde_json::to_string(&PartOfSpeech::AdjNa).unwrap();
+        assert_eq!(pos, r#""adj-na""#);
+        assert_eq!(
+            serde_json::from_str::<PartOfSpeech>(&pos).unwrap(),
+            PartOfSpeech::AdjNa
+        );
+        let conj_type = serde_json::to_string(&ConjugationType::Past).unwrap();
+        assert_eq!(conj_type, r#""Past (~ta)""#);
+        assert_eq!(
+            serde_json::from_str::<ConjugationType>(&conj_type).unwrap(),
+            ConjugationType::Past
+        );
+        let method = serde_json::to_string(&RomanizationMethod::KunreiSiki).unwrap();
+        assert_eq!(method, r#""KunreiSiki""#);
+        assert_eq!(
+            serde_json::from_str::<RomanizationMethod>(&method).unwrap(),
+            RomanizationMethod::KunreiSiki
+        );
+        let version = IchiranVersion {
+            ichiran: Some("0.1.0".to_string()),
+            lisp: "SBCL 2.3.2".to_string(),
+            entries: 210000,
+            max_seq: 10500000,
+        };
+        let json = serde_json::to_value(&version).unwrap();
+        assert_eq!(json["max_seq"], 10500000);
+        assert_eq!(
+            serde_json::from_value::<IchiranVersion>(json).unwrap(),
+            version
+        );
+    }
+
+    #[test]
+    fn fixtures_have_no_unknown_fields() {
+        for entry in std::fs::read_dir("./fixtures/full").unwrap() {
+            let json = std::fs::read_to_string(entry.unwrap().path()).unwrap();
+            let raw = parse_full_split_info(&json).unwrap();
+            assert_eq!(raw.unknown_fields(), BTreeSet::new());
+        }
+    }
+
+    #[test]
+    fn reports_unknown_fields() {
+        let mut backend = FixtureBackend::new();
+        backend.insert(
+            &["-f"],
+            "一覧",
+            r#"[[[[["ichiran",{"reading":"一覧 【いちらん】","text":"一覧","kana":"いちらん","score":352,"seq":1160790,"frequency":12,"gloss":[{"pos":"[n]","gloss":"list","note":"x"}]},[{"new":true}]]],352]]]"#,
+        );
+        let cli = IchiranCli::with_backend(backend);
+        let raw = cli.segment_raw("一覧", None).unwrap();
+        assert_eq!(
+            raw.unknown_fields(),
+            BTreeSet::from([
+                "Gloss.note".to_string(),
+                "Word.2".to_string(),
+                "WordInfo.frequency".to_string()
+            ])
+        );
+        let raw::Segment::Segmentations(segmentations) = &raw.0[0] else {
             panic!();
         };
-        assert_eq!(segmentations.len(), 1);
-        let segmented = ichiran.segment("一人目", Some(2)).unwrap();
-        let Segment::Segmentations(segmentations) = &segmented[0] else {
+        let raw::Alternatives::WordInfo(raw::Alternative::WordInfo(info)) =
+            &segmentations[0].0[0].1
+        else {
             panic!();
         };
-        assert_eq!(segmentations.len(), 2);
+        assert_eq!(info.extra["frequency"], 12);
+        // the rusty types are unaffected
+        assert_eq!(cli.segment("一覧", None).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn errors_on_missing_fixture() {
+        let err = fixtures().romanize("未知").unwrap_err();
+        let IchiranError::NotRecorded { args, input } = err else {
+            panic!("{err:?}");
+        };
+        assert!(args.is_empty());
+        assert_eq!(input, "未知");
+    }
+
+    #[test]
+    fn uses_backend() {
+        struct Fake;
+        impl Backend for Fake {
+            fn run(&self, args: &[&str], input: &str) -> Result<(String, String), IchiranError> {
+                match args {
+                    ["-f", "-l", "2"] => Ok((format!("[\"{input}\"]\n"), String::new())),
+                    [] => Ok((format!("{input}\n"), String::new())),
+                    _ => panic!("{args:?}"),
+                }
+            }
+        }
+
+        let mut ichiran = IchiranCli::with_backend(Fake);
+        ichiran.set_default_limit(Some(2));
+        let segmented = ichiran.segment("。", None).unwrap();
+        assert_eq!(segmented, vec![Segment::Other("。".to_string())]);
+        let romanized = ichiran.romanize("test").unwrap();
+        assert_eq!(romanized, "test");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn builder_configures_command() {
+        let cli = IchiranCli::builder("sh")
+            .args(["-c", "echo \"$GREETING $(basename \"$PWD\") $*\"", "sh"])
+            .env("GREETING", "hello")
+            .current_dir("src")
+            .default_limit(3)
+            .build();
+        let out = cli.romanize("x").unwrap();
+        assert_eq!(out, "hello src x");
+        let out = cli.backend().run(&["-f"], "y").unwrap().0;
+        assert_eq!(out, "hello src -f y\n");
+        let err = cli.segment("z", None).unwrap_err();
+        let IchiranError::Deserialization(_) = err else {
+            panic!("{err:?}");
+        };
+        assert_eq!(cli.limit(None), Some(3));
+        assert_eq!(cli.limit(Some(1)), Some(1));
     }
 
     #[test]
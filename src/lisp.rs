@@ -0,0 +1,170 @@
+//! Contains functionality for building Lisp expressions evaluated by `ichiran-cli -e`.
+
+use crate::{raw, Backend, Gloss, IchiranCli, IchiranError};
+use std::fmt;
+
+/// Formats the input as a Lisp string literal, escaping `"` and `\`,
+/// so that it can be safely embedded in an expression passed to [`IchiranCli::eval`].
+pub fn string(input: &str) -> String {
+    let mut s = String::with_capacity(input.len() + 2);
+    s.push('"');
+    for c in input.chars() {
+        if c == '"' || c == '\\' {
+            s.push('\\');
+        }
+        s.push(c);
+    }
+    s.push('"');
+    s
+}
+
+/// The romanization methods supported by ichiran.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RomanizationMethod {
+    HepburnBasic,
+    HepburnSimple,
+    HepburnPassport,
+    /// The default method used by `ichiran-cli`.
+    HepburnTraditional,
+    KunreiSiki,
+}
+
+impl RomanizationMethod {
+    /// Returns the Lisp variable holding the method.
+    fn variable(self) -> &'static str {
+        match self {
+            Self::HepburnBasic => "ichiran/romanize:*hepburn-basic*",
+            Self::HepburnSimple => "ichiran/romanize:*hepburn-simple*",
+            Self::HepburnPassport => "ichiran/romanize:*hepburn-passport*",
+            Self::HepburnTraditional => "ichiran/romanize:*hepburn-traditional*",
+            Self::KunreiSiki => "ichiran/romanize:*kunrei-siki*",
+        }
+    }
+}
+
+impl fmt::Display for RomanizationMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::HepburnBasic => "Hepburn (basic)",
+            Self::HepburnSimple => "Hepburn (simple)",
+            Self::HepburnPassport => "Hepburn (passport)",
+            Self::HepburnTraditional => "Hepburn (traditional)",
+            Self::KunreiSiki => "Kunrei-shiki",
+        })
+    }
+}
+
+impl<B: Backend> IchiranCli<B> {
+    /// Evaluates a Lisp expression with `ichiran-cli -e` and returns the printed result without the trailing newline.
+    ///
+    /// Strings from untrusted sources must be escaped with [`string`] before they are embedded in the expression.
+    pub fn eval(&self, expr: &str) -> Result<String, IchiranError> {
+        let (stdout, _stderr) = self.backend.run(&["-e"], expr)?;
+        Ok(crate::parse_romanize(stdout))
+    }
+
+    /// Converts katakana in the input to hiragana.
+    pub fn to_hiragana(&self, input: &str) -> Result<String, IchiranError> {
+        self.eval(&format!(
+            "(ichiran/characters:as-hiragana {})",
+            string(input)
+        ))
+    }
+
+    /// Converts hiragana in the input to katakana.
+    pub fn to_katakana(&self, input: &str) -> Result<String, IchiranError> {
+        self.eval(&format!(
+            "(ichiran/characters:as-katakana {})",
+            string(input)
+        ))
+    }
+
+    /// Equivalent to [`IchiranCli::romanize`], but with the given romanization method.
+    pub fn romanize_with_method(
+        &self,
+        input: &str,
+        method: RomanizationMethod,
+    ) -> Result<String, IchiranError> {
+        self.eval(&format!(
+            "(ichiran:romanize {} :method {})",
+            string(input),
+            method.variable()
+        ))
+    }
+
+    /// Returns the senses of the dictionary entry with the given JMdict sequence number.
+    /// The result is empty if there is no such entry.
+    pub fn senses(&self, seq: i32) -> Result<Vec<Gloss>, IchiranError> {
+        let stdout = self.eval(&format!(
+            "(jsown:to-json (ichiran/dict::get-senses-json {seq}))"
+        ))?;
+        let jd = &mut serde_json::Deserializer::from_str(&stdout);
+        let glosses: Vec<raw::Gloss> = serde_path_to_error::deserialize(jd)?;
+        Ok(glosses.into_iter().map(Into::into).collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::FixtureBackend;
+
+    fn fixture(expr: &str, stdout: &str) -> IchiranCli<FixtureBackend> {
+        let mut backend = FixtureBackend::new();
+        backend.insert(&["-e"], expr, stdout);
+        IchiranCli::with_backend(backend)
+    }
+
+    #[test]
+    fn escapes_strings() {
+        assert_eq!(string("一覧"), r#""一覧""#);
+        assert_eq!(string(r#"a"b\c"#), r#""a\"b\\c""#);
+        assert_eq!(
+            string(r#"") (uiop:run-program "ls") (""#),
+            r#""\") (uiop:run-program \"ls\") (\"""#
+        );
+    }
+
+    #[test]
+    fn evaluates_expressions() {
+        let cli = fixture("(+ 1 2)", "3\n");
+        assert_eq!(cli.eval("(+ 1 2)").unwrap(), "3");
+    }
+
+    #[test]
+    fn converts_kana() {
+        let cli = fixture(
+            r#"(ichiran/characters:as-hiragana "テスト\"")"#,
+            "てすと\"\n",
+        );
+        assert_eq!(cli.to_hiragana("テスト\"").unwrap(), "てすと\"");
+        let cli = fixture(r#"(ichiran/characters:as-katakana "てすと")"#, "テスト\n");
+        assert_eq!(cli.to_katakana("てすと").unwrap(), "テスト");
+    }
+
+    #[test]
+    fn romanizes_with_method() {
+        let cli = fixture(
+            r#"(ichiran:romanize "最高" :method ichiran/romanize:*kunrei-siki*)"#,
+            "saikô\n",
+        );
+        assert_eq!(
+            cli.romanize_with_method("最高", RomanizationMethod::KunreiSiki)
+                .unwrap(),
+            "saikô"
+        );
+    }
+
+    #[test]
+    fn looks_up_senses() {
+        let cli = fixture(
+            "(jsown:to-json (ichiran/dict::get-senses-json 1160790))",
+            r#"[{"pos":"[n,vs,vt]","gloss":"look; glance"},{"pos":"[n]","gloss":"summary; list"}]"#,
+        );
+        let senses = cli.senses(1160790).unwrap();
+        assert_eq!(senses.len(), 2);
+        assert_eq!(senses[1].gloss, "summary; list");
+        assert_eq!(senses[1].pos, "[n]");
+    }
+}
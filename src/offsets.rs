@@ -0,0 +1,420 @@
+//! Contains functionality for mapping segmentation results back to the input text.
+
+use crate::{Alternative, Segment, Word};
+use std::ops::Range;
+
+/// How many characters past the expected position are searched for a word that does not appear where expected.
+const SEARCH_WINDOW: usize = 32;
+
+/// A part of the input text, as byte and char offsets.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Span {
+    pub bytes: Range<usize>,
+    pub chars: Range<usize>,
+}
+
+impl Span {
+    /// Returns the part of the input covered by the span.
+    pub fn text<'a>(&self, input: &'a str) -> &'a str {
+        &input[self.bytes.clone()]
+    }
+}
+
+/// A [`Segment`] with the part of the input it covers.
+///
+/// With the `serde` feature, serialized as `{"type": "segmentations", "span": {...}, "segmentations": [...]}`
+/// or `{"type": "other", "span": {...}, "text": "..."}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "snake_case"))]
+pub enum SpannedSegment {
+    /// See [`Segment::Segmentations`].
+    Segmentations {
+        /// The span of the words in the first segmentation.
+        span: Span,
+        segmentations: Vec<SpannedSegmentation>,
+    },
+    /// See [`Segment::Other`].
+    Other {
+        /// The span of the input between the neighbouring words.
+        span: Span,
+        /// The text as output by ichiran, which may differ from the input.
+        text: String,
+    },
+}
+
+impl SpannedSegment {
+    /// Returns the part of the input the segment covers.
+    pub fn span(&self) -> &Span {
+        match self {
+            Self::Segmentations { span, .. } | Self::Other { span, .. } => span,
+        }
+    }
+}
+
+/// A [`crate::Segmentation`] where each word has the part of the input it covers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SpannedSegmentation {
+    pub words: Vec<SpannedWord>,
+    /// See [`crate::Segmentation::score`].
+    pub score: i32,
+}
+
+/// A [`Word`] with the part of the input it covers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SpannedWord {
+    pub word: Word,
+    /// `None` if the word could not be found in the input.
+    pub span: Option<Span>,
+}
+
+/// Computes the spans that each segment and word cover in the input the segments were created from.
+///
+/// The spans are computed by aligning the text of each word against the input.
+/// Whitespace skipped by ichiran is not included in the spans of words,
+/// and differences such as katakana written as hiragana or full-width characters written as half-width are tolerated.
+/// Words that cannot be found near their expected position have no span.
+/// [`Segment::Other`] contains text romanized by ichiran, so its span is the input between the neighbouring words.
+pub fn align(input: &str, segments: Vec<Segment>) -> Vec<SpannedSegment> {
+    let spans = spans(input, &segments);
+    segments
+        .into_iter()
+        .zip(spans)
+        .map(|(segment, spans)| match segment {
+            Segment::Segmentations(segmentations) => SpannedSegment::Segmentations {
+                span: spans.span,
+                segmentations: segmentations
+                    .into_iter()
+                    .zip(spans.words)
+                    .map(|(segmentation, spans)| SpannedSegmentation {
+                        words: segmentation
+                            .words
+                            .into_iter()
+                            .zip(spans)
+                            .map(|(word, span)| SpannedWord { word, span })
+                            .collect(),
+                        score: segmentation.score,
+                    })
+                    .collect(),
+            },
+            Segment::Other(text) => SpannedSegment::Other {
+                span: spans.span,
+                text,
+            },
+        })
+        .collect()
+}
+
+/// The spans of a [`Segment`] and its words.
+pub(crate) struct SegmentSpans {
+    /// The span of the whole segment.
+    pub(crate) span: Span,
+    /// The spans of the words in each segmentation, in the same order as [`Segment::Segmentations`].
+    /// Empty for [`Segment::Other`].
+    pub(crate) words: Vec<Vec<Option<Span>>>,
+}
+
+/// Computes the spans of the segments without taking ownership of them. See [`align`].
+pub(crate) fn spans(input: &str, segments: &[Segment]) -> Vec<SegmentSpans> {
+    let offsets = Offsets::new(input);
+    let mut cursor = 0;
+    let mut word_spans = vec![];
+    for segment in segments {
+        match segment {
+            Segment::Segmentations(segmentations) => {
+                let start = cursor;
+                let mut spans = vec![];
+                for (idx, segmentation) in segmentations.iter().enumerate() {
+                    let mut word_cursor = start;
+                    let mut words = vec![];
+                    for word in &segmentation.words {
+                        let bytes = find_word(input, word_cursor, word_text(word));
+                        if let Some(bytes) = &bytes {
+                            word_cursor = bytes.end;
+                        }
+                        words.push(bytes.map(|bytes| offsets.span(bytes)));
+                    }
+                    // the first segmentation is the most likely to be correct
+                    if idx == 0 {
+                        cursor = word_cursor;
+                    }
+                    spans.push(words);
+                }
+                word_spans.push(Some(spans));
+            }
+            Segment::Other(_) => word_spans.push(None),
+        }
+    }
+
+    let mut aligned = Vec::with_capacity(segments.len());
+    let mut prev_end = 0;
+    for (idx, words) in word_spans.iter().enumerate() {
+        match words {
+            Some(words) => {
+                let start = first_start(words).unwrap_or(prev_end);
+                let end = words
+                    .first()
+                    .and_then(|w| w.iter().flatten().last())
+                    .map_or(start, |s| s.bytes.end);
+                aligned.push(SegmentSpans {
+                    span: offsets.span(start..end),
+                    words: words.clone(),
+                });
+                prev_end = end;
+            }
+            None => {
+                // non-word text covers the gap until the next word
+                let next_start = word_spans[idx + 1..]
+                    .iter()
+                    .flatten()
+                    .find_map(|w| first_start(w))
+                    .unwrap_or(input.len())
+                    .max(prev_end);
+                aligned.push(SegmentSpans {
+                    span: offsets.span(prev_end..next_start),
+                    words: vec![],
+                });
+                prev_end = next_start;
+            }
+        }
+    }
+    aligned
+}
+
+/// Returns the start of the first word in the first segmentation that was found in the input.
+fn first_start(words: &[Vec<Option<Span>>]) -> Option<usize> {
+    words
+        .first()?
+        .iter()
+        .flatten()
+        .next()
+        .map(|s| s.bytes.start)
+}
+
+/// Returns the text of the word as it appeared in the input.
+fn word_text(word: &Word) -> &str {
+    match word.alternatives.first() {
+        Some(Alternative::WordInfo(info)) => &info.text,
+        Some(Alternative::CompoundWordInfo(info)) => &info.text,
+        None => "",
+    }
+}
+
+/// Finds the byte range of the word in the input, starting the search from the cursor.
+/// Returns `None` if the word does not appear at or shortly after the cursor.
+fn find_word(input: &str, cursor: usize, text: &str) -> Option<Range<usize>> {
+    let rest = &input[cursor..];
+    let start = cursor + (rest.len() - rest.trim_start().len());
+    let rest = &input[start..];
+
+    // matches exactly or after normalization at the expected position
+    if let Some(len) = match_len(rest, text) {
+        return Some(start..start + len);
+    }
+    // ichiran skipped some characters
+    rest.char_indices()
+        .skip(1)
+        .take(SEARCH_WINDOW)
+        .find_map(|(offset, _)| {
+            match_len(&rest[offset..], text).map(|len| start + offset..start + offset + len)
+        })
+}
+
+/// If the input starts with the text, returns the length of the matching part of the input in bytes.
+fn match_len(input: &str, text: &str) -> Option<usize> {
+    if text.is_empty() {
+        return None;
+    }
+    let mut input_chars = input.char_indices();
+    for t in text.chars() {
+        let (_, i) = input_chars.next()?;
+        if normalize(i) != normalize(t) {
+            return None;
+        }
+    }
+    Some(input_chars.next().map_or(input.len(), |(idx, _)| idx))
+}
+
+/// Normalizes characters that ichiran may output differently from the input.
+fn normalize(c: char) -> char {
+    match c {
+        // katakana to hiragana
+        'ァ'..='ヶ' => char::from_u32(c as u32 - 0x60).unwrap_or(c),
+        // full-width to half-width ASCII
+        '！'..='～' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+        '　' => ' ',
+        _ => c,
+    }
+}
+
+/// Converts byte offsets to char offsets.
+struct Offsets {
+    char_starts: Vec<usize>,
+}
+
+impl Offsets {
+    fn new(input: &str) -> Self {
+        let char_starts = input
+            .char_indices()
+            .map(|(idx, _)| idx)
+            .chain(std::iter::once(input.len()))
+            .collect();
+        Self { char_starts }
+    }
+
+    fn span(&self, bytes: Range<usize>) -> Span {
+        let chars = self.char_idx(bytes.start)..self.char_idx(bytes.end);
+        Span { bytes, chars }
+    }
+
+    fn char_idx(&self, byte_idx: usize) -> usize {
+        match self.char_starts.binary_search(&byte_idx) {
+            Ok(idx) | Err(idx) => idx,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{testing::fixtures, Segmentation, WordInfo};
+
+    fn words(texts: &[&str]) -> Segment {
+        let words = texts
+            .iter()
+            .map(|text| Word {
+                romanized: String::new(),
+                alternatives: vec![Alternative::WordInfo(WordInfo {
+                    reading: String::new(),
+                    text: text.to_string(),
+                    kana: String::new(),
+                    score: 0,
+                    counter: None,
+                    seq: None,
+                    gloss: vec![],
+                    suffix: None,
+                    conj: vec![],
+                })],
+            })
+            .collect();
+        Segment::Segmentations(vec![Segmentation { words, score: 0 }])
+    }
+
+    fn texts<'a>(input: &'a str, spanned: &[SpannedSegment]) -> Vec<Vec<&'a str>> {
+        spanned
+            .iter()
+            .map(|segment| match segment {
+                SpannedSegment::Segmentations { segmentations, .. } => segmentations[0]
+                    .words
+                    .iter()
+                    .map(|w| w.span.as_ref().map_or("", |s| s.text(input)))
+                    .collect(),
+                SpannedSegment::Other { span, .. } => vec![span.text(input)],
+            })
+            .collect()
+    }
+
+    fn first_words(segment: &SpannedSegment) -> &[SpannedWord] {
+        match segment {
+            SpannedSegment::Segmentations { segmentations, .. } => &segmentations[0].words,
+            other => panic!("{other:?}"),
+        }
+    }
+
+    #[test]
+    fn aligns_words_and_punctuation() {
+        let input = "「東京」に行きました。";
+        let spanned = fixtures().segment_with_spans(input, None).unwrap();
+        assert_eq!(
+            spanned
+                .iter()
+                .map(|s| matches!(s, SpannedSegment::Other { .. }))
+                .collect::<Vec<_>>(),
+            [true, false, true, false, true]
+        );
+        assert_eq!(
+            texts(input, &spanned),
+            [
+                vec!["「"],
+                vec!["東京"],
+                vec!["」"],
+                vec!["に", "行きました"],
+                vec!["。"]
+            ]
+        );
+        assert_eq!(spanned[3].span().chars, 4..10);
+        assert_eq!(spanned[3].span().bytes, 12..30);
+        let ikimashita = &first_words(&spanned[3])[1];
+        assert_eq!(ikimashita.span.as_ref().unwrap().chars, 5..10);
+        assert_eq!(ikimashita.word.romanized, "ikimashita");
+    }
+
+    #[test]
+    fn aligns_alternative_segmentations() {
+        let input = "一人目";
+        let spanned = fixtures().segment_with_spans(input, Some(2)).unwrap();
+        let SpannedSegment::Segmentations { segmentations, .. } = &spanned[0] else {
+            panic!("{spanned:?}");
+        };
+        let words = segmentations
+            .iter()
+            .map(|s| {
+                s.words
+                    .iter()
+                    .map(|w| w.span.as_ref().unwrap().text(input))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(words, [vec!["一人目"], vec!["一人", "目"]]);
+    }
+
+    #[test]
+    fn skips_whitespace() {
+        let input = " 一覧\u{3000}は\n最高 ";
+        let spanned = align(input, vec![words(&["一覧", "は", "最高"])]);
+        assert_eq!(texts(input, &spanned), [vec!["一覧", "は", "最高"]]);
+        assert_eq!(spanned[0].span().chars, 1..8);
+    }
+
+    #[test]
+    fn tolerates_normalization() {
+        let input = "ｔｅｓｔはテスト";
+        let spanned = align(input, vec![words(&["test", "は", "てすと"])]);
+        assert_eq!(texts(input, &spanned), [vec!["ｔｅｓｔ", "は", "テスト"]]);
+    }
+
+    #[test]
+    fn tolerates_skipped_characters() {
+        let input = "一覧♪は最高";
+        let spanned = align(
+            input,
+            vec![
+                words(&["一覧"]),
+                Segment::Other(" ".to_string()),
+                words(&["は", "最高"]),
+            ],
+        );
+        assert_eq!(
+            texts(input, &spanned),
+            [vec!["一覧"], vec!["♪"], vec!["は", "最高"]]
+        );
+
+        let input = "一覧♪は最高";
+        let spanned = align(input, vec![words(&["一覧", "は", "最高"])]);
+        assert_eq!(texts(input, &spanned), [vec!["一覧", "は", "最高"]]);
+    }
+
+    #[test]
+    fn leaves_unaligned_words_without_span() {
+        let input = "一覧は最高";
+        let spanned = align(input, vec![words(&["いちらん", "は", "最高"])]);
+        let words = first_words(&spanned[0]);
+        assert_eq!(words[0].span, None);
+        assert_eq!(words[1].span.as_ref().unwrap().text(input), "は");
+        assert_eq!(words[2].span.as_ref().unwrap().text(input), "最高");
+        assert_eq!(spanned[0].span().text(input), "は最高");
+    }
+}
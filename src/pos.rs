@@ -0,0 +1,259 @@
+//! Contains the part of speech tags used by JMdict.
+
+use std::{convert::Infallible, fmt, str::FromStr};
+
+macro_rules! parts_of_speech {
+    ($($variant:ident => $tag:literal, $doc:literal;)*) => {
+        /// A JMdict part of speech tag, such as `n` or `adj-na`.
+        ///
+        /// Tags that are not recognised are kept as [`PartOfSpeech::Unknown`].
+        /// With the `serde` feature, serialized as the tag string.
+        #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+        pub enum PartOfSpeech {
+            $(
+                #[doc = $doc]
+                $variant,
+            )*
+            /// A tag that is not part of the JMdict entity set.
+            Unknown(String),
+        }
+
+        impl PartOfSpeech {
+            /// Returns the tag as written by JMdict and ichiran.
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $(Self::$variant => $tag,)*
+                    Self::Unknown(tag) => tag,
+                }
+            }
+        }
+
+        impl From<&str> for PartOfSpeech {
+            fn from(s: &str) -> Self {
+                match s {
+                    $($tag => Self::$variant,)*
+                    _ => Self::Unknown(s.to_string()),
+                }
+            }
+        }
+
+        impl FromStr for PartOfSpeech {
+            type Err = Infallible;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                Ok(Self::from(s))
+            }
+        }
+    };
+}
+
+parts_of_speech! {
+    AdjF => "adj-f", "noun or verb acting prenominally";
+    AdjI => "adj-i", "adjective (keiyoushi)";
+    AdjIx => "adj-ix", "adjective (keiyoushi) - yoi/ii class";
+    AdjKari => "adj-kari", "'kari' adjective (archaic)";
+    AdjKu => "adj-ku", "'ku' adjective (archaic)";
+    AdjNa => "adj-na", "adjectival nouns or quasi-adjectives (keiyodoshi)";
+    AdjNari => "adj-nari", "archaic/formal form of na-adjective";
+    AdjNo => "adj-no", "nouns which may take the genitive case particle 'no'";
+    AdjPn => "adj-pn", "pre-noun adjectival (rentaishi)";
+    AdjShiku => "adj-shiku", "'shiku' adjective (archaic)";
+    AdjT => "adj-t", "'taru' adjective";
+    Adv => "adv", "adverb (fukushi)";
+    AdvTo => "adv-to", "adverb taking the 'to' particle";
+    Aux => "aux", "auxiliary";
+    AuxAdj => "aux-adj", "auxiliary adjective";
+    AuxV => "aux-v", "auxiliary verb";
+    Conj => "conj", "conjunction";
+    Cop => "cop", "copula";
+    CopDa => "cop-da", "copula, as tagged by older versions of JMdict";
+    Ctr => "ctr", "counter";
+    Exp => "exp", "expressions (phrases, clauses, etc.)";
+    Int => "int", "interjection (kandoushi)";
+    N => "n", "noun (common) (futsuumeishi)";
+    NAdv => "n-adv", "adverbial noun (fukushitekimeishi)";
+    NPr => "n-pr", "proper noun";
+    NPref => "n-pref", "noun, used as a prefix";
+    NSuf => "n-suf", "noun, used as a suffix";
+    NT => "n-t", "noun (temporal) (jisoumeishi)";
+    Num => "num", "numeric";
+    Pn => "pn", "pronoun";
+    Pref => "pref", "prefix";
+    Prt => "prt", "particle";
+    Suf => "suf", "suffix";
+    Unc => "unc", "unclassified";
+    VUnspec => "v-unspec", "verb unspecified";
+    V1 => "v1", "Ichidan verb";
+    V1S => "v1-s", "Ichidan verb - kureru special class";
+    V2aS => "v2a-s", "Nidan verb with 'u' ending (archaic)";
+    V2bK => "v2b-k", "Nidan verb (upper class) with 'bu' ending (archaic)";
+    V2bS => "v2b-s", "Nidan verb (lower class) with 'bu' ending (archaic)";
+    V2dK => "v2d-k", "Nidan verb (upper class) with 'dzu' ending (archaic)";
+    V2dS => "v2d-s", "Nidan verb (lower class) with 'dzu' ending (archaic)";
+    V2gK => "v2g-k", "Nidan verb (upper class) with 'gu' ending (archaic)";
+    V2gS => "v2g-s", "Nidan verb (lower class) with 'gu' ending (archaic)";
+    V2hK => "v2h-k", "Nidan verb (upper class) with 'hu/fu' ending (archaic)";
+    V2hS => "v2h-s", "Nidan verb (lower class) with 'hu/fu' ending (archaic)";
+    V2kK => "v2k-k", "Nidan verb (upper class) with 'ku' ending (archaic)";
+    V2kS => "v2k-s", "Nidan verb (lower class) with 'ku' ending (archaic)";
+    V2mK => "v2m-k", "Nidan verb (upper class) with 'mu' ending (archaic)";
+    V2mS => "v2m-s", "Nidan verb (lower class) with 'mu' ending (archaic)";
+    V2nS => "v2n-s", "Nidan verb (lower class) with 'nu' ending (archaic)";
+    V2rK => "v2r-k", "Nidan verb (upper class) with 'ru' ending (archaic)";
+    V2rS => "v2r-s", "Nidan verb (lower class) with 'ru' ending (archaic)";
+    V2sS => "v2s-s", "Nidan verb (lower class) with 'su' ending (archaic)";
+    V2tK => "v2t-k", "Nidan verb (upper class) with 'tsu' ending (archaic)";
+    V2tS => "v2t-s", "Nidan verb (lower class) with 'tsu' ending (archaic)";
+    V2wS => "v2w-s", "Nidan verb (lower class) with 'u' ending and 'we' conjugation (archaic)";
+    V2yK => "v2y-k", "Nidan verb (upper class) with 'yu' ending (archaic)";
+    V2yS => "v2y-s", "Nidan verb (lower class) with 'yu' ending (archaic)";
+    V2zS => "v2z-s", "Nidan verb (lower class) with 'zu' ending (archaic)";
+    V4b => "v4b", "Yodan verb with 'bu' ending (archaic)";
+    V4g => "v4g", "Yodan verb with 'gu' ending (archaic)";
+    V4h => "v4h", "Yodan verb with 'hu/fu' ending (archaic)";
+    V4k => "v4k", "Yodan verb with 'ku' ending (archaic)";
+    V4m => "v4m", "Yodan verb with 'mu' ending (archaic)";
+    V4n => "v4n", "Yodan verb with 'nu' ending (archaic)";
+    V4r => "v4r", "Yodan verb with 'ru' ending (archaic)";
+    V4s => "v4s", "Yodan verb with 'su' ending (archaic)";
+    V4t => "v4t", "Yodan verb with 'tsu' ending (archaic)";
+    V5aru => "v5aru", "Godan verb - -aru special class";
+    V5b => "v5b", "Godan verb with 'bu' ending";
+    V5g => "v5g", "Godan verb with 'gu' ending";
+    V5k => "v5k", "Godan verb with 'ku' ending";
+    V5kS => "v5k-s", "Godan verb - Iku/Yuku special class";
+    V5m => "v5m", "Godan verb with 'mu' ending";
+    V5n => "v5n", "Godan verb with 'nu' ending";
+    V5r => "v5r", "Godan verb with 'ru' ending";
+    V5rI => "v5r-i", "Godan verb with 'ru' ending (irregular verb)";
+    V5s => "v5s", "Godan verb with 'su' ending";
+    V5t => "v5t", "Godan verb with 'tsu' ending";
+    V5u => "v5u", "Godan verb with 'u' ending";
+    V5uS => "v5u-s", "Godan verb with 'u' ending (special class)";
+    V5uru => "v5uru", "Godan verb - Uru old class verb (old form of Eru)";
+    Vi => "vi", "intransitive verb";
+    Vk => "vk", "Kuru verb - special class";
+    Vn => "vn", "irregular nu verb";
+    Vr => "vr", "irregular ru verb, plain form ends with -ri";
+    Vs => "vs", "noun or participle which takes the aux. verb suru";
+    VsC => "vs-c", "su verb - precursor to the modern suru";
+    VsI => "vs-i", "suru verb - included";
+    VsS => "vs-s", "suru verb - special class";
+    Vt => "vt", "transitive verb";
+    Vz => "vz", "Ichidan verb - zuru verb (alternative form of -jiru verbs)";
+}
+
+impl PartOfSpeech {
+    /// Parses a comma separated list of tags such as `adj-no,adj-na,n`,
+    /// optionally surrounded by square brackets.
+    pub fn parse_list(s: &str) -> Vec<Self> {
+        let s = s.trim();
+        let s = s
+            .strip_prefix('[')
+            .and_then(|s| s.strip_suffix(']'))
+            .unwrap_or(s);
+        s.split(',')
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .map(PartOfSpeech::from)
+            .collect()
+    }
+
+    /// Returns true for conjugating verb classes. Transitivity tags such as `vt` and `vs` are not included.
+    pub fn is_verb(&self) -> bool {
+        let tag = self.as_str();
+        !matches!(self, Self::Unknown(_) | Self::Vi | Self::Vt | Self::Vs)
+            && (tag.starts_with('v') || tag == "aux-v")
+    }
+
+    /// Returns true for adjectives, including adjectival nouns.
+    pub fn is_adjective(&self) -> bool {
+        !matches!(self, Self::Unknown(_))
+            && (self.as_str().starts_with("adj-") || matches!(self, Self::AuxAdj))
+    }
+
+    /// Returns true for nouns and pronouns.
+    pub fn is_noun(&self) -> bool {
+        !matches!(self, Self::Unknown(_)) && (self.as_str().starts_with('n') && *self != Self::Num)
+            || *self == Self::Pn
+    }
+}
+
+impl fmt::Display for PartOfSpeech {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for PartOfSpeech {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for PartOfSpeech {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Ok(Self::from(s.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::{self, word_info};
+
+    #[test]
+    fn parses_tags() {
+        assert_eq!("n".parse(), Ok(PartOfSpeech::N));
+        assert_eq!("adj-na".parse(), Ok(PartOfSpeech::AdjNa));
+        assert_eq!("v5k-s".parse(), Ok(PartOfSpeech::V5kS));
+        assert_eq!(
+            "unknown-tag".parse(),
+            Ok(PartOfSpeech::Unknown("unknown-tag".to_string()))
+        );
+        assert_eq!(PartOfSpeech::from("cop"), PartOfSpeech::Cop);
+        assert_eq!(PartOfSpeech::AuxV.to_string(), "aux-v");
+        assert_eq!(PartOfSpeech::Unknown("x".to_string()).to_string(), "x");
+    }
+
+    #[test]
+    fn parses_lists() {
+        assert_eq!(
+            PartOfSpeech::parse_list("[adj-no,adj-na,n]"),
+            [PartOfSpeech::AdjNo, PartOfSpeech::AdjNa, PartOfSpeech::N]
+        );
+        assert_eq!(PartOfSpeech::parse_list("cop"), [PartOfSpeech::Cop]);
+        assert_eq!(PartOfSpeech::parse_list("[]"), []);
+    }
+
+    #[test]
+    fn classifies_tags() {
+        assert!(PartOfSpeech::V1.is_verb());
+        assert!(PartOfSpeech::V5kS.is_verb());
+        assert!(!PartOfSpeech::Vt.is_verb());
+        assert!(!PartOfSpeech::N.is_verb());
+        assert!(PartOfSpeech::AdjI.is_adjective());
+        assert!(!PartOfSpeech::Adv.is_adjective());
+        assert!(PartOfSpeech::NAdv.is_noun());
+        assert!(PartOfSpeech::Pn.is_noun());
+        assert!(!PartOfSpeech::Num.is_noun());
+        assert!(!PartOfSpeech::Unknown("n".to_string()).is_noun());
+    }
+
+    #[test]
+    fn parses_glosses_and_conjugations() {
+        let segmented = testing::fixtures()
+            .segment("「東京」に行きました。", None)
+            .unwrap();
+        let ni = word_info(&segmented, 3, 0);
+        assert_eq!(ni.gloss[0].parts_of_speech(), [PartOfSpeech::Prt]);
+        let ikimashita = word_info(&segmented, 3, 1);
+        assert_eq!(
+            ikimashita.conj[0].prop[0].part_of_speech(),
+            PartOfSpeech::V5kS
+        );
+    }
+}
@@ -0,0 +1,156 @@
+//! Contains helpers for running `ichiran-cli` processes.
+
+use crate::IchiranError;
+use std::{
+    io::Read,
+    process::{Command, Output, Stdio},
+    sync::{Arc, Mutex},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+/// How often a running process is checked for completion when a timeout is set.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Runs the command to completion and collects its output.
+/// If the timeout is exceeded, the process is killed and [`IchiranError::Timeout`] is returned.
+/// The timeout also covers reading the output after the process exits,
+/// since a grandchild process can keep the pipes open after the process itself is done.
+pub(crate) fn output(
+    mut command: Command,
+    timeout: Option<Duration>,
+) -> Result<Output, IchiranError> {
+    let timeout = match timeout {
+        Some(timeout) => timeout,
+        None => return command.output().map_err(IchiranError::CommandError),
+    };
+
+    let start = Instant::now();
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(IchiranError::CommandError)?;
+    let (stdout, stdout_reader) =
+        read_in_background(child.stdout.take().expect("stdout is piped"), usize::MAX);
+    let (stderr, stderr_reader) =
+        read_in_background(child.stderr.take().expect("stderr is piped"), usize::MAX);
+    let mut status = None;
+    loop {
+        if status.is_none() {
+            status = child.try_wait().map_err(IchiranError::CommandError)?;
+        }
+        if let Some(status) = status {
+            if stdout_reader.is_finished() && stderr_reader.is_finished() {
+                let _ = stdout_reader.join();
+                let _ = stderr_reader.join();
+                return Ok(Output {
+                    status,
+                    stdout: snapshot(&stdout),
+                    stderr: snapshot(&stderr),
+                });
+            }
+        }
+        let elapsed = start.elapsed();
+        if elapsed >= timeout {
+            if status.is_none() {
+                let _ = child.kill();
+                let _ = child.wait();
+            }
+            // the readers are not joined in case a grandchild process is keeping the pipes open
+            let stderr = String::from_utf8_lossy(&snapshot(&stderr)).into_owned();
+            return Err(IchiranError::Timeout { elapsed, stderr });
+        }
+        thread::sleep(POLL_INTERVAL.min(timeout - elapsed));
+    }
+}
+
+/// Continuously reads from the pipe on a separate thread so that the child process never blocks on a full pipe.
+/// Only the last `max_len` bytes are kept.
+pub(crate) fn read_in_background(
+    mut pipe: impl Read + Send + 'static,
+    max_len: usize,
+) -> (Arc<Mutex<Vec<u8>>>, JoinHandle<()>) {
+    let buf = Arc::new(Mutex::new(Vec::new()));
+    let writer = Arc::clone(&buf);
+    let handle = thread::spawn(move || {
+        let mut chunk = [0; 1024];
+        while let Ok(read) = pipe.read(&mut chunk) {
+            if read == 0 {
+                break;
+            }
+            let mut buf = lock(&writer);
+            buf.extend_from_slice(&chunk[..read]);
+            if buf.len() > max_len {
+                let excess = buf.len() - max_len;
+                buf.drain(..excess);
+            }
+        }
+    });
+    (buf, handle)
+}
+
+/// Copies the current contents of the buffer.
+pub(crate) fn snapshot(buf: &Mutex<Vec<u8>>) -> Vec<u8> {
+    lock(buf).clone()
+}
+
+pub(crate) fn lock(buf: &Mutex<Vec<u8>>) -> std::sync::MutexGuard<'_, Vec<u8>> {
+    buf.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn kills_on_timeout() {
+        let mut command = Command::new("sh");
+        command.args(["-c", "echo partial >&2; sleep 10"]);
+        let err = output(command, Some(Duration::from_millis(500))).unwrap_err();
+        let IchiranError::Timeout { elapsed, stderr } = err else {
+            panic!("{err:?}");
+        };
+        assert!(elapsed >= Duration::from_millis(500));
+        assert!(elapsed < Duration::from_secs(10));
+        assert_eq!(stderr, "partial\n");
+    }
+
+    #[test]
+    fn does_not_wait_for_grandchildren_past_timeout() {
+        let mut command = Command::new("sh");
+        // the background sleep inherits the pipes and keeps them open after sh exits
+        command.args(["-c", "echo partial >&2; sleep 5 &"]);
+        let err = output(command, Some(Duration::from_millis(500))).unwrap_err();
+        let IchiranError::Timeout { elapsed, stderr } = err else {
+            panic!("{err:?}");
+        };
+        assert!(elapsed >= Duration::from_millis(500));
+        assert!(elapsed < Duration::from_secs(5));
+        assert_eq!(stderr, "partial\n");
+    }
+
+    #[test]
+    fn finishes_before_timeout() {
+        let mut command = Command::new("sh");
+        command.args(["-c", "echo out; echo err >&2"]);
+        let out = output(command, Some(Duration::from_secs(10))).unwrap();
+        assert!(out.status.success());
+        assert_eq!(out.stdout, b"out\n");
+        assert_eq!(out.stderr, b"err\n");
+    }
+
+    #[test]
+    fn keeps_tail_of_output() {
+        let mut child = Command::new("sh")
+            .args(["-c", "printf 0123456789"])
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let (stdout, reader) = read_in_background(child.stdout.take().unwrap(), 4);
+        reader.join().unwrap();
+        child.wait().unwrap();
+        assert_eq!(snapshot(&stdout), b"6789");
+    }
+}
@@ -1,14 +1,21 @@
 //! Contains the "raw" types that directly correspond to the output of `ichiran-cli`.
+//!
+//! The structs that correspond to JSON objects keep the keys that are not recognised by this version of the crate
+//! in their `extra` field, so no data is lost and the original JSON can be reproduced with the `serde` feature.
+//! The unrecognised keys of a whole output can be listed with [`FullSplitInfo::unknown_fields`].
 
 use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::BTreeSet;
 
 #[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[serde(transparent)]
 pub struct FullSplitInfo(pub Vec<Segment>);
 
 #[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[serde(untagged)]
-#[cfg_attr(test, serde(deny_unknown_fields))]
 pub enum Segment {
     /// Japanese
     Segmentations(Vec<Segmentation>),
@@ -17,7 +24,7 @@ pub enum Segment {
 }
 
 #[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
-#[cfg_attr(test, serde(deny_unknown_fields))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Segmentation(
     /// Words
     pub Vec<Word>,
@@ -26,26 +33,28 @@ pub struct Segmentation(
 );
 
 #[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
-#[cfg_attr(test, serde(deny_unknown_fields))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Word(
     /// Romanized
     pub String,
     /// One or more alternative WordInfo
     pub Alternatives,
-    /// Unknown, seems to always be empty
-    pub Vec<serde_json::Value>,
+    /// Unknown, seems to always be empty.
+    /// Reported by [`FullSplitInfo::unknown_fields`] when it is not.
+    pub Vec<Value>,
 );
 
 #[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
-#[cfg_attr(test, serde(deny_unknown_fields))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[serde(untagged)]
+#[allow(clippy::large_enum_variant)]
 pub enum Alternatives {
     WordInfo(Alternative),
     Alternatives { alternative: Vec<Alternative> },
 }
 
 #[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
-#[cfg_attr(test, serde(deny_unknown_fields))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[serde(untagged)]
 pub enum Alternative {
     // note: this order is important so that
@@ -55,23 +64,28 @@ pub enum Alternative {
 }
 
 #[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
-#[cfg_attr(test, serde(deny_unknown_fields))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct WordInfo {
     pub reading: String,
     pub text: String,
     pub kana: String,
     pub score: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub counter: Option<Counter>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub seq: Option<i32>,
-    #[serde(default)]
-    pub gloss: Vec<Gloss>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub suffix: Option<String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub gloss: Vec<Gloss>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub conj: Vec<Conj>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 #[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
-#[cfg_attr(test, serde(deny_unknown_fields))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct CompoundWordInfo {
     pub reading: String,
     pub text: String,
@@ -79,78 +93,173 @@ pub struct CompoundWordInfo {
     pub score: i32,
     pub compound: Vec<String>,
     pub components: Vec<WordInfo>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 #[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
-#[cfg_attr(test, serde(deny_unknown_fields))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Counter {
     pub value: String,
     pub ordinal: Ordinal,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 #[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
-#[cfg_attr(test, serde(deny_unknown_fields))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[serde(untagged)]
 pub enum Ordinal {
     Bool(bool),
-    Vec(Vec<serde_json::Value>),
+    Vec(Vec<Value>),
 }
 
 #[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
-#[cfg_attr(test, serde(deny_unknown_fields))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Gloss {
     pub pos: String,
     pub gloss: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub field: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub info: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 #[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
-#[cfg_attr(test, serde(deny_unknown_fields))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Conj {
     pub prop: Vec<ConjProp>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub via: Vec<Via>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub reading: Option<String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub gloss: Vec<Gloss>,
     pub readok: Readok,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 #[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
-#[cfg_attr(test, serde(deny_unknown_fields))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ConjProp {
     pub pos: String,
     #[serde(rename = "type")]
     pub prop_type: PropType,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
     pub fml: bool,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
     pub neg: bool,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 #[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
-#[cfg_attr(test, serde(deny_unknown_fields))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[serde(untagged)]
 pub enum PropType {
     String(String),
-    Vec(Vec<serde_json::Value>),
+    Vec(Vec<Value>),
 }
 
 #[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
-#[cfg_attr(test, serde(deny_unknown_fields))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Via {
     pub prop: Vec<ConjProp>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub via: Vec<Via>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub reading: Option<String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub gloss: Vec<Gloss>,
     pub readok: Readok,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 #[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
-#[cfg_attr(test, serde(deny_unknown_fields))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[serde(untagged)]
 pub enum Readok {
     Bool(bool),
-    Vec(Vec<serde_json::Value>),
+    Vec(Vec<Value>),
+}
+
+impl FullSplitInfo {
+    /// Returns the unrecognised fields in the output as `Type.field`, such as `WordInfo.frequency`.
+    /// A non-empty third element of a [`Word`] is reported as `Word.2`.
+    ///
+    /// A non-empty result likely means that the output format of ichiran has changed and the crate should be updated.
+    pub fn unknown_fields(&self) -> BTreeSet<String> {
+        let mut unknown = BTreeSet::new();
+        for segment in &self.0 {
+            if let Segment::Segmentations(segmentations) = segment {
+                for word in segmentations.iter().flat_map(|s| &s.0) {
+                    if !word.2.is_empty() {
+                        unknown.insert("Word.2".to_string());
+                    }
+                    match &word.1 {
+                        Alternatives::WordInfo(alternative) => {
+                            alternative.collect_unknown(&mut unknown)
+                        }
+                        Alternatives::Alternatives { alternative } => {
+                            for alternative in alternative {
+                                alternative.collect_unknown(&mut unknown);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        unknown
+    }
+}
+
+fn insert_keys(unknown: &mut BTreeSet<String>, ty: &str, extra: &Map<String, Value>) {
+    unknown.extend(extra.keys().map(|key| format!("{ty}.{key}")));
+}
+
+impl Alternative {
+    fn collect_unknown(&self, unknown: &mut BTreeSet<String>) {
+        match self {
+            Self::CompoundWordInfo(info) => {
+                insert_keys(unknown, "CompoundWordInfo", &info.extra);
+                for component in &info.components {
+                    component.collect_unknown(unknown);
+                }
+            }
+            Self::WordInfo(info) => info.collect_unknown(unknown),
+        }
+    }
+}
+
+impl WordInfo {
+    fn collect_unknown(&self, unknown: &mut BTreeSet<String>) {
+        insert_keys(unknown, "WordInfo", &self.extra);
+        if let Some(counter) = &self.counter {
+            insert_keys(unknown, "Counter", &counter.extra);
+        }
+        for gloss in &self.gloss {
+            insert_keys(unknown, "Gloss", &gloss.extra);
+        }
+        for conj in &self.conj {
+            insert_keys(unknown, "Conj", &conj.extra);
+            collect_conj(unknown, &conj.prop, &conj.via, &conj.gloss);
+        }
+    }
+}
+
+fn collect_conj(unknown: &mut BTreeSet<String>, prop: &[ConjProp], via: &[Via], gloss: &[Gloss]) {
+    for prop in prop {
+        insert_keys(unknown, "ConjProp", &prop.extra);
+    }
+    for gloss in gloss {
+        insert_keys(unknown, "Gloss", &gloss.extra);
+    }
+    for via in via {
+        insert_keys(unknown, "Via", &via.extra);
+        collect_conj(unknown, &via.prop, &via.via, &via.gloss);
+    }
 }
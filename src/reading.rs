@@ -0,0 +1,98 @@
+//! Contains a parsed version of the reading strings printed by ichiran.
+
+use std::fmt;
+
+/// A reading such as `いい天気 【いいてんき】`, split into the headword and the bracketed kana.
+///
+/// The headword is the dictionary spelling, which may differ from the text of the word in the input,
+/// for example when the input is written in kana.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Reading {
+    pub headword: String,
+    /// Missing if the headword is already written in kana.
+    pub kana: Option<String>,
+}
+
+impl Reading {
+    /// Parses a reading of the form `headword 【kana】` or `headword`.
+    pub fn parse(reading: &str) -> Self {
+        let (headword, kana) = split(reading);
+        Self {
+            headword: headword.to_string(),
+            kana: kana.map(str::to_string),
+        }
+    }
+
+    /// Returns the kana, or the headword if it is already written in kana.
+    pub fn kana_or_headword(&self) -> &str {
+        self.kana.as_deref().unwrap_or(&self.headword)
+    }
+}
+
+impl fmt::Display for Reading {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kana {
+            Some(kana) => write!(f, "{} 【{kana}】", self.headword),
+            None => f.write_str(&self.headword),
+        }
+    }
+}
+
+/// Splits a reading into its headword and kana without allocating.
+pub(crate) fn split(reading: &str) -> (&str, Option<&str>) {
+    match reading
+        .split_once(" 【")
+        .and_then(|(headword, rest)| Some((headword, rest.strip_suffix('】')?)))
+    {
+        Some((headword, kana)) => (headword, Some(kana)),
+        None => (reading, None),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{testing, Alternative};
+
+    #[test]
+    fn parses_readings() {
+        let reading = Reading::parse("いい天気 【いいてんき】");
+        assert_eq!(reading.headword, "いい天気");
+        assert_eq!(reading.kana.as_deref(), Some("いいてんき"));
+        assert_eq!(reading.to_string(), "いい天気 【いいてんき】");
+
+        let reading = Reading::parse("だ");
+        assert_eq!(reading.headword, "だ");
+        assert_eq!(reading.kana, None);
+        assert_eq!(reading.kana_or_headword(), "だ");
+        assert_eq!(reading.to_string(), "だ");
+    }
+
+    #[test]
+    fn flags_differing_headwords() {
+        let cli = testing::fixtures();
+        let segmented = cli.segment("はし", None).unwrap();
+        let headwords = testing::alternatives(&segmented, 0, 0)
+            .iter()
+            .map(|alternative| {
+                let Alternative::WordInfo(info) = alternative else {
+                    panic!();
+                };
+                assert!(info.reading_differs());
+                info.parsed_reading().headword
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(headwords, ["橋", "箸"]);
+
+        let segmented = cli.segment("一人目", None).unwrap();
+        let info = testing::word_info(&segmented, 0, 0);
+        assert!(!info.reading_differs());
+        assert_eq!(info.parsed_reading().kana.as_deref(), Some("ひとりめ"));
+
+        let segmented = cli.segment("食べたい", None).unwrap();
+        let info = testing::compound_word_info(&segmented, 0, 0);
+        assert!(!info.reading_differs());
+        assert_eq!(info.parsed_reading().headword, "食べたい");
+    }
+}
@@ -0,0 +1,176 @@
+//! Contains backends for recording `ichiran-cli` calls to a file and replaying them later.
+
+use crate::{Backend, IchiranError};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::Path,
+    sync::Mutex,
+};
+
+/// A single recorded call to `ichiran-cli`.
+/// Recordings are stored as JSON lines, with one exchange per line.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Exchange {
+    pub args: Vec<String>,
+    pub input: String,
+    pub stdout: String,
+    pub stderr: String,
+    /// Whether `ichiran-cli` exited successfully.
+    pub success: bool,
+}
+
+/// A [`Backend`] that passes calls through to another backend and appends every exchange to a file.
+/// The file can later be served with [`ReplayBackend`].
+///
+/// Calls that fail without producing output, such as timeouts, are not recorded.
+#[derive(Debug)]
+pub struct RecordingBackend<B> {
+    inner: B,
+    file: Mutex<File>,
+}
+
+impl<B: Backend> RecordingBackend<B> {
+    /// Records the calls made through `inner`, appending them to the file at the given path.
+    pub fn new(inner: B, path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            inner,
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Returns the backend calls are passed through to.
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+
+    fn record(&self, exchange: &Exchange) -> Result<(), IchiranError> {
+        let mut line = serde_json::to_string(exchange).expect("exchanges can always be serialized");
+        line.push('\n');
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        file.write_all(line.as_bytes())
+            .and_then(|_| file.flush())
+            .map_err(IchiranError::RecordingError)
+    }
+}
+
+impl<B: Backend> Backend for RecordingBackend<B> {
+    fn run(&self, args: &[&str], input: &str) -> Result<(String, String), IchiranError> {
+        let res = self.inner.run(args, input);
+        let (stdout, stderr, success) = match &res {
+            Ok((stdout, stderr)) => (stdout, stderr, true),
+            Err(IchiranError::IchiranError { stdout, stderr }) => (stdout, stderr, false),
+            Err(_) => return res,
+        };
+        self.record(&Exchange {
+            args: args.iter().map(|a| a.to_string()).collect(),
+            input: input.to_string(),
+            stdout: stdout.clone(),
+            stderr: stderr.clone(),
+            success,
+        })?;
+        res
+    }
+}
+
+/// A [`Backend`] that serves the exchanges recorded by a [`RecordingBackend`] instead of calling `ichiran-cli`.
+/// Calls that were not recorded return [`IchiranError::NotRecorded`].
+/// If the same call was recorded more than once, the latest exchange is used.
+#[derive(Debug, Clone, Default)]
+pub struct ReplayBackend {
+    exchanges: HashMap<(Vec<String>, String), Exchange>,
+}
+
+impl ReplayBackend {
+    /// Loads the exchanges from a file written by [`RecordingBackend`].
+    pub fn from_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = BufReader::new(File::open(path)?);
+        let mut exchanges = vec![];
+        for line in file.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let exchange = serde_json::from_str(&line)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            exchanges.push(exchange);
+        }
+        Ok(Self::from_exchanges(exchanges))
+    }
+
+    /// Creates a backend that serves the given exchanges.
+    pub fn from_exchanges(exchanges: impl IntoIterator<Item = Exchange>) -> Self {
+        let exchanges = exchanges
+            .into_iter()
+            .map(|e| ((e.args.clone(), e.input.clone()), e))
+            .collect();
+        Self { exchanges }
+    }
+}
+
+impl Backend for ReplayBackend {
+    fn run(&self, args: &[&str], input: &str) -> Result<(String, String), IchiranError> {
+        let key = (
+            args.iter().map(|a| a.to_string()).collect::<Vec<_>>(),
+            input.to_string(),
+        );
+        match self.exchanges.get(&key) {
+            Some(exchange) if exchange.success => {
+                Ok((exchange.stdout.clone(), exchange.stderr.clone()))
+            }
+            Some(exchange) => Err(IchiranError::IchiranError {
+                stdout: exchange.stdout.clone(),
+                stderr: exchange.stderr.clone(),
+            }),
+            None => Err(IchiranError::NotRecorded {
+                args: key.0,
+                input: key.1,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{testing, IchiranCli};
+
+    #[test]
+    fn replays_recording() {
+        let path =
+            std::env::temp_dir().join(format!("ichiran-rs-recording-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let fixtures = testing::fixture_backend();
+        let recording = IchiranCli::with_backend(RecordingBackend::new(fixtures, &path).unwrap());
+        let segmented = recording.segment("一人目", Some(2)).unwrap();
+        let romanized = recording.romanize("一覧は最高だぞ").unwrap();
+        recording.romanize("未知").unwrap_err();
+
+        let replay = IchiranCli::with_backend(ReplayBackend::from_file(&path).unwrap());
+        assert_eq!(replay.segment("一人目", Some(2)).unwrap(), segmented);
+        assert_eq!(replay.romanize("一覧は最高だぞ").unwrap(), romanized);
+        let err = replay.romanize("未知").unwrap_err();
+        assert!(matches!(err, IchiranError::NotRecorded { .. }));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn replays_failures() {
+        let replay = ReplayBackend::from_exchanges([Exchange {
+            args: vec!["-i".to_string()],
+            input: "x".to_string(),
+            stdout: String::new(),
+            stderr: "database error".to_string(),
+            success: false,
+        }]);
+        let err = replay.run(&["-i"], "x").unwrap_err();
+        let IchiranError::IchiranError { stderr, .. } = err else {
+            panic!("{err:?}");
+        };
+        assert_eq!(stderr, "database error");
+    }
+}
@@ -0,0 +1,146 @@
+//! Contains the parts of `ichiran-cli` calls that do not depend on how the calls are made,
+//! shared by [`crate::IchiranCli`], [`crate::AsyncIchiranCli`] and [`crate::IchiranSession`].
+
+use crate::{
+    chunk::{self, Piece},
+    IchiranError, RomanizedWithInfo, Segment,
+};
+
+/// The output requested from `ichiran-cli`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Mode {
+    /// `-f`, with the limit passed to `-l`.
+    Full(Option<u32>),
+    /// `-i`.
+    Info,
+    /// No flags.
+    Plain,
+}
+
+impl Mode {
+    /// Returns the arguments passed to `ichiran-cli` for the mode.
+    pub(crate) fn args(self) -> Vec<String> {
+        match self {
+            Self::Full(Some(limit)) => vec!["-f".to_string(), "-l".to_string(), limit.to_string()],
+            Self::Full(None) => vec!["-f".to_string()],
+            Self::Info => vec!["-i".to_string()],
+            Self::Plain => vec![],
+        }
+    }
+}
+
+/// A request for the output of `ichiran-cli` for an input,
+/// which is split into chunks if it is longer than the max chunk length.
+///
+/// The caller runs `ichiran-cli` for each of the [`Request::inputs`] and passes the stdout of the calls back
+/// to the method that parses them.
+#[derive(Debug)]
+pub(crate) struct Request<'a> {
+    pub(crate) mode: Mode,
+    pieces: Vec<Piece<'a>>,
+}
+
+impl<'a> Request<'a> {
+    pub(crate) fn new(mode: Mode, input: &'a str, max_chunk_len: Option<usize>) -> Self {
+        let pieces = match max_chunk_len {
+            Some(max_chunk_len) if input.len() > max_chunk_len => {
+                chunk::pieces(input, max_chunk_len)
+            }
+            _ => vec![Piece::Text(input)],
+        };
+        Self { mode, pieces }
+    }
+
+    /// Returns the inputs `ichiran-cli` needs to be called with, in order.
+    pub(crate) fn inputs(&self) -> impl Iterator<Item = &'a str> + '_ {
+        self.pieces.iter().filter_map(|piece| match piece {
+            Piece::Text(text) => Some(*text),
+            Piece::Whitespace(_) => None,
+        })
+    }
+
+    /// Parses the stdout of `ichiran-cli -f` for each input and stitches the results together.
+    pub(crate) fn segment(&self, stdouts: Vec<String>) -> Result<Vec<Segment>, IchiranError> {
+        self.parse(
+            stdouts,
+            |stdout| crate::parse_segment(&stdout),
+            chunk::stitch,
+        )
+    }
+
+    /// Parses the stdout of `ichiran-cli -i` for each input and joins the results together.
+    pub(crate) fn romanize_with_info(
+        &self,
+        stdouts: Vec<String>,
+    ) -> Result<RomanizedWithInfo, IchiranError> {
+        self.parse(
+            stdouts,
+            crate::parse_romanize_with_info,
+            chunk::join_with_info,
+        )
+    }
+
+    /// Parses the stdout of `ichiran-cli` for each input and joins the results together.
+    pub(crate) fn romanize(&self, stdouts: Vec<String>) -> Result<String, IchiranError> {
+        self.parse(
+            stdouts,
+            |stdout| Ok(crate::parse_romanize(stdout)),
+            chunk::join,
+        )
+    }
+
+    /// Parses the stdout of each input, and combines the results if the input was split into chunks.
+    fn parse<T>(
+        &self,
+        stdouts: Vec<String>,
+        mut parse: impl FnMut(String) -> Result<T, IchiranError>,
+        combine: impl FnOnce(Vec<Piece<'a, T>>) -> T,
+    ) -> Result<T, IchiranError> {
+        let mut stdouts = stdouts.into_iter();
+        let mut next = || stdouts.next().expect("a stdout for each input");
+        if let [Piece::Text(_)] = self.pieces.as_slice() {
+            return parse(next());
+        }
+        let pieces = self
+            .pieces
+            .iter()
+            .map(|piece| piece.try_map(|_| parse(next())))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(combine(pieces))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn builds_args() {
+        assert_eq!(Mode::Full(Some(2)).args(), ["-f", "-l", "2"]);
+        assert_eq!(Mode::Full(None).args(), ["-f"]);
+        assert_eq!(Mode::Info.args(), ["-i"]);
+        assert!(Mode::Plain.args().is_empty());
+    }
+
+    #[test]
+    fn splits_only_long_inputs() {
+        let input = "いい天気ですね。\n一人目";
+        let request = Request::new(Mode::Plain, input, None);
+        assert_eq!(request.inputs().collect::<Vec<_>>(), [input]);
+        let request = Request::new(Mode::Plain, input, Some(input.len()));
+        assert_eq!(request.inputs().collect::<Vec<_>>(), [input]);
+
+        let request = Request::new(Mode::Plain, input, Some(30));
+        assert_eq!(
+            request.inputs().collect::<Vec<_>>(),
+            ["いい天気ですね。", "一人目"]
+        );
+        let romanized = request
+            .romanize(vec![
+                "iitenki desu ne.\n".to_string(),
+                "hitorime\n".to_string(),
+            ])
+            .unwrap();
+        assert_eq!(romanized, "iitenki desu ne.\nhitorime");
+    }
+}
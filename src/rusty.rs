@@ -1,10 +1,17 @@
 //! Contains more Rusty equivalents of the raw types.
 
-use crate::raw;
+use crate::{raw, ConjugationType, PartOfSpeech, Reading};
 
 /// A single segment which may consist of one or more words,
 /// or punctuation or other non-word text.
+///
+/// With the `serde` feature, serialized as `{"type": "segmentations", "value": [...]}` or `{"type": "other", "value": "..."}`.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "type", content = "value", rename_all = "snake_case")
+)]
 pub enum Segment {
     /// A list of alternate segmentations for a sequence of words.
     Segmentations(Vec<Segmentation>),
@@ -29,6 +36,7 @@ impl From<raw::FullSplitInfo> for Vec<Segment> {
 
 /// A possible segmentation for a sequence of one or more words.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Segmentation {
     pub words: Vec<Word>,
     /// A higher score indicates that this segmentation is more likely to be correct.
@@ -45,6 +53,7 @@ impl From<raw::Segmentation> for Segmentation {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Word {
     pub romanized: String,
     /// Possible interpretations for this word.
@@ -60,7 +69,13 @@ impl From<raw::Word> for Word {
     }
 }
 
+/// With the `serde` feature, serialized as `{"type": "word_info", "value": {...}}` or `{"type": "compound_word_info", "value": {...}}`.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "type", content = "value", rename_all = "snake_case")
+)]
 pub enum Alternative {
     WordInfo(WordInfo),
     CompoundWordInfo(CompoundWordInfo),
@@ -89,6 +104,7 @@ impl From<raw::Alternative> for Alternative {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct WordInfo {
     pub reading: String,
     pub text: String,
@@ -101,6 +117,19 @@ pub struct WordInfo {
     pub conj: Vec<Conj>,
 }
 
+impl WordInfo {
+    /// Parses [`WordInfo::reading`].
+    pub fn parsed_reading(&self) -> Reading {
+        Reading::parse(&self.reading)
+    }
+
+    /// Returns true if the headword of the reading is spelled differently from [`WordInfo::text`],
+    /// for example when the input was written in kana.
+    pub fn reading_differs(&self) -> bool {
+        crate::reading::split(&self.reading).0 != self.text
+    }
+}
+
 impl From<raw::WordInfo> for WordInfo {
     fn from(value: raw::WordInfo) -> Self {
         Self {
@@ -118,6 +147,7 @@ impl From<raw::WordInfo> for WordInfo {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CompoundWordInfo {
     pub reading: String,
     pub text: String,
@@ -127,6 +157,18 @@ pub struct CompoundWordInfo {
     pub components: Vec<WordInfo>,
 }
 
+impl CompoundWordInfo {
+    /// Parses [`CompoundWordInfo::reading`].
+    pub fn parsed_reading(&self) -> Reading {
+        Reading::parse(&self.reading)
+    }
+
+    /// Returns true if the headword of the reading is spelled differently from [`CompoundWordInfo::text`].
+    pub fn reading_differs(&self) -> bool {
+        crate::reading::split(&self.reading).0 != self.text
+    }
+}
+
 impl From<raw::CompoundWordInfo> for CompoundWordInfo {
     fn from(value: raw::CompoundWordInfo) -> Self {
         Self {
@@ -141,6 +183,7 @@ impl From<raw::CompoundWordInfo> for CompoundWordInfo {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Counter {
     pub value: String,
     pub ordinal: bool,
@@ -159,6 +202,7 @@ impl From<raw::Counter> for Counter {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Gloss {
     pub pos: String,
     pub gloss: String,
@@ -166,6 +210,13 @@ pub struct Gloss {
     pub info: Option<String>,
 }
 
+impl Gloss {
+    /// Parses the bracketed list of part of speech tags in [`Gloss::pos`].
+    pub fn parts_of_speech(&self) -> Vec<PartOfSpeech> {
+        PartOfSpeech::parse_list(&self.pos)
+    }
+}
+
 impl From<raw::Gloss> for Gloss {
     fn from(value: raw::Gloss) -> Self {
         Self {
@@ -178,6 +229,7 @@ impl From<raw::Gloss> for Gloss {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Conj {
     pub prop: Vec<ConjProp>,
     pub via: Vec<Via>,
@@ -186,6 +238,36 @@ pub struct Conj {
     pub readok: bool,
 }
 
+impl Conj {
+    /// Returns true if the outermost conjugation is [`ConjugationType::Past`].
+    pub fn is_past(&self) -> bool {
+        self.prop
+            .iter()
+            .any(|p| p.conjugation_type() == Some(ConjugationType::Past))
+    }
+
+    /// Returns true if the outermost conjugation is negative.
+    pub fn is_negative(&self) -> bool {
+        self.prop.iter().any(|p| p.neg)
+    }
+
+    /// Returns true if the outermost conjugation is formal.
+    pub fn is_formal(&self) -> bool {
+        self.prop.iter().any(|p| p.fml)
+    }
+
+    /// Returns true if the conjugation type appears anywhere in the conjugation, including every level of [`Conj::via`].
+    pub fn has_type(&self, conjugation_type: &ConjugationType) -> bool {
+        has_type(&self.prop, conjugation_type)
+            || self.via.iter().any(|v| v.has_type(conjugation_type))
+    }
+}
+
+fn has_type(prop: &[ConjProp], conjugation_type: &ConjugationType) -> bool {
+    prop.iter()
+        .any(|p| p.conjugation_type().as_ref() == Some(conjugation_type))
+}
+
 impl From<raw::Conj> for Conj {
     fn from(value: raw::Conj) -> Self {
         Self {
@@ -208,6 +290,7 @@ impl From<raw::Readok> for bool {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ConjProp {
     pub pos: String,
     pub prop_type: Option<String>,
@@ -215,6 +298,18 @@ pub struct ConjProp {
     pub neg: bool,
 }
 
+impl ConjProp {
+    /// Parses [`ConjProp::pos`].
+    pub fn part_of_speech(&self) -> PartOfSpeech {
+        PartOfSpeech::from(self.pos.as_str())
+    }
+
+    /// Parses [`ConjProp::prop_type`].
+    pub fn conjugation_type(&self) -> Option<ConjugationType> {
+        self.prop_type.as_deref().map(ConjugationType::from)
+    }
+}
+
 impl From<raw::ConjProp> for ConjProp {
     fn from(value: raw::ConjProp) -> Self {
         Self {
@@ -236,17 +331,29 @@ impl From<raw::PropType> for Option<String> {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Via {
     pub prop: Vec<ConjProp>,
+    /// The conjugation this one was applied to, for words that were conjugated more than once.
+    pub via: Vec<Via>,
     pub reading: Option<String>,
     pub gloss: Vec<Gloss>,
     pub readok: bool,
 }
 
+impl Via {
+    /// Returns true if the conjugation type appears in this conjugation or any of the nested [`Via::via`].
+    pub fn has_type(&self, conjugation_type: &ConjugationType) -> bool {
+        has_type(&self.prop, conjugation_type)
+            || self.via.iter().any(|v| v.has_type(conjugation_type))
+    }
+}
+
 impl From<raw::Via> for Via {
     fn from(value: raw::Via) -> Self {
         Self {
             prop: value.prop.into_iter().map(Into::into).collect(),
+            via: value.via.into_iter().map(Into::into).collect(),
             reading: value.reading,
             gloss: value.gloss.into_iter().map(Into::into).collect(),
             readok: value.readok.into(),
@@ -0,0 +1,433 @@
+//! Contains a persistent `ichiran-cli` session.
+
+use crate::{
+    lisp, process,
+    request::{Mode, Request},
+    IchiranError, RomanizedWithInfo, Segment,
+};
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// The max amount of stderr kept for error messages, since a session can run for a long time.
+const STDERR_LIMIT: usize = 64 * 1024;
+
+/// Lisp program evaluated by `ichiran-cli -e` that keeps reading requests of the form `(mode input limit)` from stdin.
+/// The output of each request is identical to the output of the corresponding `ichiran-cli` call,
+/// and is framed by a header line containing the number of lines in the output.
+const SESSION_LOOP: &str = r#"
+(let ((*read-eval* nil))
+  (loop for request = (read *standard-input* nil nil)
+        while request
+        do (destructuring-bind (mode input limit) request
+             (handler-case
+                 (let ((out (with-output-to-string (*standard-output*)
+                              (case mode
+                                (:full
+                                 (format t "~a~%" (jsown:to-json (ichiran:romanize* input :limit limit))))
+                                (:info
+                                 (multiple-value-bind (romanized info) (ichiran:romanize input :with-info t)
+                                   (format t "~a~%" romanized)
+                                   (loop for (word . gloss) in info
+                                         do (format t "~%* ~a  ~a~%" word gloss))))
+                                (t
+                                 (format t "~a~%" (ichiran:romanize input)))))))
+                   (format t "ok ~d~%~a" (count #\Newline out) out))
+               (error (e)
+                 (format t "error ~a~%" (substitute #\Space #\Newline (princ-to-string e)))))
+             (finish-output))))
+"#;
+
+/// A long-lived `ichiran-cli` process that handles requests one at a time,
+/// avoiding the startup cost of spawning a new process for every call.
+///
+/// The results are identical to the ones returned by the corresponding [`crate::IchiranCli`] methods,
+/// and the default limit and max chunk length of the [`crate::IchiranCli`] the session was started from are respected.
+/// If a timeout is configured, it applies to each call.
+/// The process is stopped when the session is dropped, or when a request times out or the process produces unexpected output,
+/// after which every request returns [`IchiranError::SessionExited`].
+#[derive(Debug)]
+pub struct IchiranSession {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    stdout: Receiver<Vec<u8>>,
+    stderr: Arc<Mutex<Vec<u8>>>,
+    default_limit: Option<u32>,
+    max_chunk_len: Option<usize>,
+    timeout: Option<Duration>,
+}
+
+impl IchiranSession {
+    pub(crate) fn start(
+        mut command: Command,
+        default_limit: Option<u32>,
+        max_chunk_len: Option<usize>,
+        timeout: Option<Duration>,
+    ) -> Result<Self, IchiranError> {
+        let mut child = command
+            .args(["-e", SESSION_LOOP])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(IchiranError::CommandError)?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = read_lines(child.stdout.take().expect("stdout is piped"));
+        let (stderr, _) = process::read_in_background(
+            child.stderr.take().expect("stderr is piped"),
+            STDERR_LIMIT,
+        );
+
+        Ok(Self {
+            child,
+            stdin: Some(stdin),
+            stdout,
+            stderr,
+            default_limit,
+            max_chunk_len,
+            timeout,
+        })
+    }
+
+    /// Equivalent to [`crate::IchiranCli::segment`].
+    pub fn segment(
+        &mut self,
+        input: &str,
+        limit: Option<u32>,
+    ) -> Result<Vec<Segment>, IchiranError> {
+        let mode = Mode::Full(limit.or(self.default_limit));
+        let request = Request::new(mode, input, self.max_chunk_len);
+        request.segment(self.run_request(&request)?)
+    }
+
+    /// Equivalent to [`crate::IchiranCli::romanize_with_info`].
+    pub fn romanize_with_info(&mut self, input: &str) -> Result<RomanizedWithInfo, IchiranError> {
+        let request = Request::new(Mode::Info, input, self.max_chunk_len);
+        request.romanize_with_info(self.run_request(&request)?)
+    }
+
+    /// Equivalent to [`crate::IchiranCli::romanize`].
+    pub fn romanize(&mut self, input: &str) -> Result<String, IchiranError> {
+        let request = Request::new(Mode::Plain, input, self.max_chunk_len);
+        request.romanize(self.run_request(&request)?)
+    }
+
+    /// Sends each input of the request to the session and returns the outputs.
+    fn run_request(&mut self, request: &Request) -> Result<Vec<String>, IchiranError> {
+        request
+            .inputs()
+            .map(|input| self.call(request.mode, input))
+            .collect()
+    }
+
+    /// Sends a single call to the session and returns the output.
+    /// Stops the process if the output can no longer be trusted to belong to the next call.
+    fn call(&mut self, mode: Mode, input: &str) -> Result<String, IchiranError> {
+        let result = self.exchange(mode, input);
+        match &result {
+            Ok(_) | Err(IchiranError::IchiranError { .. }) => {}
+            Err(_) => self.stop(),
+        }
+        result
+    }
+
+    fn exchange(&mut self, mode: Mode, input: &str) -> Result<String, IchiranError> {
+        let start = Instant::now();
+        let request = request_line(mode, input);
+        let written = match self.stdin.as_mut() {
+            Some(stdin) => stdin
+                .write_all(request.as_bytes())
+                .and_then(|_| stdin.flush())
+                .is_ok(),
+            None => false,
+        };
+        if !written {
+            return Err(self.exited());
+        }
+
+        let header = self.read_line(start)?;
+        if let Some(lines) = header.strip_prefix("ok ") {
+            let lines = lines
+                .trim_end()
+                .parse::<usize>()
+                .map_err(|_| IchiranError::UnexpectedOutput(header.clone()))?;
+            let mut stdout = String::new();
+            for _ in 0..lines {
+                stdout.push_str(&self.read_line(start)?);
+            }
+            Ok(stdout)
+        } else if let Some(error) = header.strip_prefix("error ") {
+            Err(IchiranError::IchiranError {
+                stdout: String::new(),
+                stderr: error.trim_end().to_string(),
+            })
+        } else {
+            Err(IchiranError::UnexpectedOutput(header))
+        }
+    }
+
+    /// Reads a single line from stdout, including the newline.
+    /// Fails if the call that started at `start` exceeds the timeout.
+    fn read_line(&mut self, start: Instant) -> Result<String, IchiranError> {
+        let line = match self.timeout {
+            Some(timeout) => {
+                let remaining = timeout.saturating_sub(start.elapsed());
+                match self.stdout.recv_timeout(remaining) {
+                    Ok(line) => line,
+                    Err(RecvTimeoutError::Timeout) => {
+                        self.stop();
+                        let stderr = self.stderr();
+                        return Err(IchiranError::Timeout {
+                            elapsed: start.elapsed(),
+                            stderr,
+                        });
+                    }
+                    Err(RecvTimeoutError::Disconnected) => return Err(self.exited()),
+                }
+            }
+            None => self.stdout.recv().map_err(|_| self.exited())?,
+        };
+        Ok(String::from_utf8(line)?)
+    }
+
+    /// Kills the process so that every following request fails with [`IchiranError::SessionExited`].
+    fn stop(&mut self) {
+        drop(self.stdin.take());
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+
+    fn exited(&self) -> IchiranError {
+        IchiranError::SessionExited {
+            stderr: self.stderr(),
+        }
+    }
+
+    fn stderr(&self) -> String {
+        String::from_utf8_lossy(&process::snapshot(&self.stderr)).into_owned()
+    }
+}
+
+/// Returns the request read by [`SESSION_LOOP`] for a call in the given mode.
+fn request_line(mode: Mode, input: &str) -> String {
+    let (mode, limit) = match mode {
+        // ichiran-cli defaults to a limit of 1
+        Mode::Full(limit) => ("full", limit.unwrap_or(1)),
+        Mode::Info => ("info", 1),
+        Mode::Plain => ("plain", 1),
+    };
+    format!("(:{mode} {} {limit})\n", lisp::string(input))
+}
+
+/// Reads lines from the pipe on a separate thread so that they can be waited for with a timeout.
+/// The channel is disconnected once the pipe is closed.
+fn read_lines(pipe: impl Read + Send + 'static) -> Receiver<Vec<u8>> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut pipe = BufReader::new(pipe);
+        loop {
+            let mut line = vec![];
+            match pipe.read_until(b'\n', &mut line) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {
+                    if sender.send(line).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+    receiver
+}
+
+impl Drop for IchiranSession {
+    fn drop(&mut self) {
+        // closing stdin ends the request loop
+        drop(self.stdin.take());
+        if self.child.try_wait().ok().flatten().is_none() {
+            let _ = self.child.kill();
+        }
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::IchiranCli;
+    use std::path::PathBuf;
+
+    /// Speaks the session protocol, with the input of each request deciding the reply.
+    #[cfg(unix)]
+    const FAKE_SESSION: &str = r#"
+while read -r request; do
+  case "$request" in
+    *'"ok"'*) echo "ok 2"; echo "first"; echo "second" ;;
+    *'"error"'*) echo "error something went wrong" ;;
+    *'"garbage"'*) echo "garbage" ;;
+    *'"slow"'*) sleep 10 ;;
+  esac
+done
+"#;
+
+    #[cfg(unix)]
+    fn fake_session(timeout: Option<Duration>) -> IchiranSession {
+        let mut command = Command::new("sh");
+        // the arguments added by start are passed to the script as positional parameters
+        command.args(["-c", FAKE_SESSION, "sh"]);
+        IchiranSession::start(command, None, None, timeout).unwrap()
+    }
+
+    /// Replies to each request with the stdout of the matching entry in `./fixtures`,
+    /// framed the same way as the output of [`SESSION_LOOP`].
+    #[cfg(unix)]
+    fn fixture_session(default_limit: Option<u32>, max_chunk_len: Option<usize>) -> IchiranSession {
+        let manifest = std::fs::read_to_string("./fixtures/fixtures.json").unwrap();
+        let manifest = serde_json::from_str::<Vec<serde_json::Value>>(&manifest).unwrap();
+        let mut script = "while read -r request; do\n  case \"$request\" in\n".to_string();
+        for fixture in manifest {
+            let args = fixture["args"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|arg| arg.as_str().unwrap())
+                .collect::<Vec<_>>();
+            let mode = match args.as_slice() {
+                ["-f"] => Mode::Full(None),
+                ["-f", "-l", limit] => Mode::Full(Some(limit.parse().unwrap())),
+                ["-i"] => Mode::Info,
+                [] => Mode::Plain,
+                other => panic!("unexpected arguments {other:?}"),
+            };
+            let request = request_line(mode, fixture["input"].as_str().unwrap());
+            script.push_str(&format!(
+                "    '{}') file=./fixtures/{} ;;\n",
+                request.trim_end(),
+                fixture["stdout"].as_str().unwrap()
+            ));
+        }
+        script.push_str("  esac\n  echo \"ok $(($(wc -l < \"$file\")))\"\n  cat \"$file\"\ndone\n");
+
+        let mut command = Command::new("sh");
+        command.args(["-c", &script, "sh"]);
+        IchiranSession::start(command, default_limit, max_chunk_len, None).unwrap()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn handles_replies() {
+        let mut session = fake_session(None);
+        assert_eq!(session.romanize("ok").unwrap(), "first\nsecond");
+        let err = session.romanize("error").unwrap_err();
+        let IchiranError::IchiranError { stderr, .. } = err else {
+            panic!("{err:?}");
+        };
+        assert_eq!(stderr, "something went wrong");
+        // errors reported by ichiran do not affect the session
+        assert_eq!(session.romanize("ok").unwrap(), "first\nsecond");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn stops_after_unexpected_output() {
+        let mut session = fake_session(None);
+        let err = session.romanize("garbage").unwrap_err();
+        assert!(matches!(err, IchiranError::UnexpectedOutput(_)), "{err:?}");
+        let err = session.romanize("ok").unwrap_err();
+        assert!(matches!(err, IchiranError::SessionExited { .. }), "{err:?}");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn stops_after_timeout() {
+        let mut session = fake_session(Some(Duration::from_millis(500)));
+        assert_eq!(session.romanize("ok").unwrap(), "first\nsecond");
+        let err = session.romanize("slow").unwrap_err();
+        let IchiranError::Timeout { elapsed, .. } = err else {
+            panic!("{err:?}");
+        };
+        assert!(elapsed >= Duration::from_millis(500));
+        assert!(elapsed < Duration::from_secs(10));
+        let err = session.romanize("ok").unwrap_err();
+        assert!(matches!(err, IchiranError::SessionExited { .. }), "{err:?}");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn reads_framed_output() {
+        let cli = crate::testing::fixtures();
+        let mut session = fixture_session(None, None);
+        for input in [
+            "一覧は最高だぞ",
+            "いい天気ですね。",
+            "「東京」に行きました。",
+        ] {
+            assert_eq!(
+                session.romanize(input).unwrap(),
+                cli.romanize(input).unwrap()
+            );
+            assert_eq!(
+                session.romanize_with_info(input).unwrap(),
+                cli.romanize_with_info(input).unwrap()
+            );
+            assert_eq!(
+                session.segment(input, None).unwrap(),
+                cli.segment(input, None).unwrap()
+            );
+        }
+        assert_eq!(
+            session.segment("一人目", Some(2)).unwrap(),
+            cli.segment("一人目", Some(2)).unwrap()
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn uses_default_limit_and_chunks() {
+        let mut cli = crate::testing::fixtures();
+        cli.set_default_limit(Some(2));
+        cli.set_max_chunk_len(Some(30));
+        let mut session = fixture_session(Some(2), Some(30));
+        assert_eq!(
+            session.segment("一人目", None).unwrap(),
+            cli.segment("一人目", None).unwrap()
+        );
+
+        let input = "いい天気ですね。\n一覧は最高だぞ";
+        assert_eq!(
+            session.romanize(input).unwrap(),
+            cli.romanize(input).unwrap()
+        );
+        assert_eq!(
+            session.romanize_with_info(input).unwrap(),
+            cli.romanize_with_info(input).unwrap()
+        );
+    }
+
+    #[test]
+    fn session_matches_cli() {
+        let cli = IchiranCli::new(PathBuf::from("./data/ichiran-cli"));
+        let mut session = cli.session().unwrap();
+        for input in ["一覧は最高だぞ", "一人目", "いい天気ですね。"] {
+            assert_eq!(
+                session.romanize(input).unwrap(),
+                cli.romanize(input).unwrap()
+            );
+            assert_eq!(
+                session.romanize_with_info(input).unwrap(),
+                cli.romanize_with_info(input).unwrap()
+            );
+            assert_eq!(
+                session.segment(input, Some(2)).unwrap(),
+                cli.segment(input, Some(2)).unwrap()
+            );
+        }
+    }
+}
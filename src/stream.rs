@@ -0,0 +1,182 @@
+//! Contains an iterator for segmenting text line by line.
+
+use crate::{Backend, IchiranCli, IchiranError, Segment};
+use std::io::{self, BufRead};
+
+/// Lazily segments each line of a reader with [`IchiranCli::segment`].
+/// Created with [`IchiranCli::segment_lines`].
+///
+/// Yields the line index of each line along with its segmentation,
+/// so that processing can be resumed from the next line with [`SegmentLines::resume_from`] after a failure.
+/// Line indices are zero-based, so the first line has the index 0.
+/// Only one line is held in memory at a time.
+///
+/// Lines that are not valid UTF-8 are yielded as [`IchiranError::ReadError`] and the following lines are still read.
+/// Other read errors are yielded once and end the iteration, since they usually persist.
+#[derive(Debug)]
+pub struct SegmentLines<'a, B, R> {
+    cli: &'a IchiranCli<B>,
+    reader: R,
+    limit: Option<u32>,
+    skip_blank: bool,
+    resume_from: usize,
+    line_index: usize,
+    done: bool,
+}
+
+impl<'a, B: Backend, R: BufRead> SegmentLines<'a, B, R> {
+    pub(crate) fn new(cli: &'a IchiranCli<B>, reader: R) -> Self {
+        Self {
+            cli,
+            reader,
+            limit: None,
+            skip_blank: true,
+            resume_from: 0,
+            line_index: 0,
+            done: false,
+        }
+    }
+
+    /// Sets the limit passed to [`IchiranCli::segment`] for each line.
+    pub fn limit(mut self, limit: Option<u32>) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Whether lines that only contain whitespace are skipped, which is the default.
+    /// If they are not skipped, they are yielded with an empty segmentation without calling `ichiran-cli`.
+    pub fn skip_blank(mut self, skip_blank: bool) -> Self {
+        self.skip_blank = skip_blank;
+        self
+    }
+
+    /// Skips the lines before the given zero-based line index.
+    pub fn resume_from(mut self, line_index: usize) -> Self {
+        self.resume_from = line_index;
+        self
+    }
+}
+
+impl<'a, B: Backend, R: BufRead> Iterator for SegmentLines<'a, B, R> {
+    type Item = (usize, Result<Vec<Segment>, IchiranError>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut buf = vec![];
+        while !self.done {
+            buf.clear();
+            let line_index = self.line_index;
+            match self.reader.read_until(b'\n', &mut buf) {
+                Ok(0) => self.done = true,
+                Ok(_) => {
+                    self.line_index += 1;
+                    if line_index < self.resume_from {
+                        continue;
+                    }
+                    let line = match std::str::from_utf8(&buf) {
+                        Ok(line) => line,
+                        Err(err) => {
+                            let err = io::Error::new(io::ErrorKind::InvalidData, err);
+                            return Some((line_index, Err(IchiranError::ReadError(err))));
+                        }
+                    };
+                    let line = line.trim_end_matches(&['\n', '\r'][..]);
+                    if line.trim().is_empty() {
+                        if self.skip_blank {
+                            continue;
+                        }
+                        return Some((line_index, Ok(vec![])));
+                    }
+                    return Some((line_index, self.cli.segment(line, self.limit)));
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => {
+                    self.done = true;
+                    return Some((line_index, Err(IchiranError::ReadError(err))));
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{testing::fixtures, IchiranError};
+    use std::io;
+
+    const TEXT: &str = "一人目\n\n  \nはし\r\n未知\n";
+
+    #[test]
+    fn segments_lines() {
+        let ichiran = fixtures();
+        let lines = ichiran.segment_lines(TEXT.as_bytes()).collect::<Vec<_>>();
+        let indices = lines.iter().map(|(index, _)| *index).collect::<Vec<_>>();
+        assert_eq!(indices, [0, 3, 4]);
+        assert_eq!(
+            lines[0].1.as_ref().unwrap(),
+            &ichiran.segment("一人目", None).unwrap()
+        );
+        assert!(lines[1].1.is_ok());
+        assert!(lines[2].1.is_err());
+    }
+
+    #[test]
+    fn passes_through_blank_lines() {
+        let ichiran = fixtures();
+        let lines = ichiran
+            .segment_lines(TEXT.as_bytes())
+            .skip_blank(false)
+            .collect::<Vec<_>>();
+        let indices = lines.iter().map(|(index, _)| *index).collect::<Vec<_>>();
+        assert_eq!(indices, [0, 1, 2, 3, 4]);
+        assert!(lines[1].1.as_ref().unwrap().is_empty());
+    }
+
+    #[test]
+    fn resumes() {
+        let ichiran = fixtures();
+        let lines = ichiran
+            .segment_lines(TEXT.as_bytes())
+            .limit(Some(2))
+            .resume_from(3)
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        assert_eq!(lines, [3, 4]);
+    }
+
+    #[test]
+    fn continues_after_invalid_utf8() {
+        let ichiran = fixtures();
+        let text = b"\xff\xfe\n\xe4\xb8\x80\xe4\xba\xba\xe7\x9b\xae\n";
+        let lines = ichiran.segment_lines(&text[..]).collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        let (index, Err(IchiranError::ReadError(err))) = &lines[0] else {
+            panic!("{lines:?}");
+        };
+        assert_eq!(*index, 0);
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(lines[1].0, 1);
+        assert_eq!(
+            lines[1].1.as_ref().unwrap(),
+            &ichiran.segment("一人目", None).unwrap()
+        );
+    }
+
+    #[test]
+    fn stops_after_read_error() {
+        struct Failing;
+
+        impl io::Read for Failing {
+            fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::new(io::ErrorKind::Other, "disk error"))
+            }
+        }
+
+        let ichiran = fixtures();
+        let lines = ichiran
+            .segment_lines(io::BufReader::new(Failing))
+            .collect::<Vec<_>>();
+        assert_eq!(lines.len(), 1);
+        assert!(matches!(lines[0], (0, Err(IchiranError::ReadError(_)))));
+    }
+}
@@ -0,0 +1,167 @@
+//! Contains functionality for detecting the version of ichiran and checking that it is compatible with the crate.
+
+use crate::{raw, Backend, IchiranCli, IchiranError};
+use serde_json::Value;
+
+/// Lisp expression evaluated by `ichiran-cli -e` that outputs a JSON object describing the installation.
+/// `jsown` outputs `nil` as `[]`.
+pub(crate) const PROBE_EXPR: &str = r#"(jsown:to-json (let ((counts (postmodern:query "SELECT count(*), max(seq) FROM entry" :row))) (jsown:new-js ("ichiran" (asdf:component-version (asdf:find-system :ichiran))) ("lisp" (format nil "~a ~a" (lisp-implementation-type) (lisp-implementation-version))) ("entries" (first counts)) ("maxSeq" (second counts)))))"#;
+
+/// Input segmented by [`IchiranCli::check_compatibility`]. Contains a word with multiple conjugations,
+/// which exercises most of the output format.
+const SAMPLE_INPUT: &str = "食べさせられなかった";
+
+/// Information about an ichiran installation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IchiranVersion {
+    /// The version of the ichiran system, if defined.
+    pub ichiran: Option<String>,
+    /// The Lisp implementation and its version.
+    pub lisp: String,
+    /// The number of entries in the dictionary database.
+    pub entries: u64,
+    /// The largest sequence number in the dictionary database, which changes when the dictionary is rebuilt.
+    pub max_seq: i64,
+}
+
+impl IchiranVersion {
+    fn parse(stdout: &str) -> Result<Self, IchiranError> {
+        let unexpected = || IchiranError::UnexpectedOutput(stdout.to_string());
+        let json = serde_json::from_str::<Value>(stdout.trim()).map_err(|_| unexpected())?;
+        // nil is output as an empty list
+        let string = |key: &str| json.get(key).and_then(Value::as_str).map(str::to_string);
+        Ok(Self {
+            ichiran: string("ichiran"),
+            lisp: string("lisp").ok_or_else(unexpected)?,
+            entries: json
+                .get("entries")
+                .and_then(Value::as_u64)
+                .ok_or_else(unexpected)?,
+            max_seq: json
+                .get("maxSeq")
+                .and_then(Value::as_i64)
+                .ok_or_else(unexpected)?,
+        })
+    }
+}
+
+impl std::fmt::Display for IchiranVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "ichiran {}",
+            self.ichiran.as_deref().unwrap_or("(unknown)")
+        )?;
+        write!(
+            f,
+            ", {}, {} entries up to seq {}",
+            self.lisp, self.entries, self.max_seq
+        )
+    }
+}
+
+impl<B: Backend> IchiranCli<B> {
+    /// Detects the version of ichiran, the Lisp implementation it runs on and the size of its dictionary
+    /// by evaluating Lisp with `ichiran-cli -e`.
+    /// The probe only reads the loaded ichiran system and its database, and does not run any other programs.
+    pub fn probe(&self) -> Result<IchiranVersion, IchiranError> {
+        let (stdout, _stderr) = self.backend.run(&["-e"], PROBE_EXPR)?;
+        IchiranVersion::parse(&stdout)
+    }
+
+    /// Calls [`IchiranCli::probe`] and checks that the output of `ichiran-cli -f` can be parsed by this crate
+    /// by segmenting a sample sentence.
+    ///
+    /// Returns [`IchiranError::Incompatible`] if the output format is not supported.
+    /// Fields that are not recognised do not cause an error, see [`raw::FullSplitInfo::unknown_fields`].
+    ///
+    /// The check is opt-in and is never run automatically by the crate,
+    /// so it should be called once before relying on an unknown ichiran installation.
+    pub fn check_compatibility(&self) -> Result<IchiranVersion, IchiranError> {
+        let version = self.probe()?;
+        let incompatible = |reason: String| IchiranError::Incompatible {
+            version: version.to_string(),
+            reason,
+        };
+        let (stdout, _stderr) = self.backend.run(&["-f"], SAMPLE_INPUT)?;
+        let info = crate::parse_full_split_info(&stdout).map_err(|err| match err {
+            IchiranError::Deserialization(err) => incompatible(err.to_string()),
+            err => err,
+        })?;
+        let has_words = info
+            .0
+            .iter()
+            .any(|segment| matches!(segment, raw::Segment::Segmentations(s) if !s.is_empty()));
+        if !has_words {
+            return Err(incompatible(format!(
+                "no words found when segmenting {SAMPLE_INPUT}"
+            )));
+        }
+        Ok(version)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::FixtureBackend;
+
+    const PROBE_OUTPUT: &str = r#"{"ichiran":"0.1.0","lisp":"SBCL 2.3.2","entries":210000,"maxSeq":10500000}
+"#;
+
+    fn backend(full: &str) -> FixtureBackend {
+        let mut backend = FixtureBackend::new();
+        backend.insert(&["-e"], PROBE_EXPR, PROBE_OUTPUT);
+        backend.insert(&["-f"], SAMPLE_INPUT, full);
+        backend
+    }
+
+    #[test]
+    fn parses_probe_output() {
+        let cli = IchiranCli::with_backend(backend("[]"));
+        let version = cli.probe().unwrap();
+        assert_eq!(version.ichiran.as_deref(), Some("0.1.0"));
+        assert_eq!(version.lisp, "SBCL 2.3.2");
+        assert_eq!(version.entries, 210000);
+        assert_eq!(version.max_seq, 10500000);
+        assert_eq!(
+            version.to_string(),
+            "ichiran 0.1.0, SBCL 2.3.2, 210000 entries up to seq 10500000"
+        );
+
+        let version =
+            IchiranVersion::parse(r#"{"ichiran":[],"lisp":"SBCL 2.1.0","entries":1,"maxSeq":2}"#)
+                .unwrap();
+        assert_eq!(version.ichiran, None);
+        assert!(matches!(
+            IchiranVersion::parse("T"),
+            Err(IchiranError::UnexpectedOutput(_))
+        ));
+    }
+
+    #[test]
+    fn accepts_supported_output() {
+        let full = std::fs::read_to_string("./fixtures/full/tabesaserarenakatta.json").unwrap();
+        let cli = IchiranCli::with_backend(backend(&full));
+        assert_eq!(cli.check_compatibility().unwrap(), cli.probe().unwrap());
+    }
+
+    #[test]
+    fn rejects_unsupported_output() {
+        // the score is missing from the segmentation
+        let cli = IchiranCli::with_backend(backend(r#"[[[[["tabesaserarenakatta",{}]]]]]"#));
+        let err = cli.check_compatibility().unwrap_err();
+        let IchiranError::Incompatible { version, reason } = err else {
+            panic!("{err:?}");
+        };
+        assert!(version.starts_with("ichiran 0.1.0"));
+        assert!(!reason.is_empty());
+
+        let cli = IchiranCli::with_backend(backend("[]"));
+        assert!(matches!(
+            cli.check_compatibility(),
+            Err(IchiranError::Incompatible { .. })
+        ));
+    }
+}